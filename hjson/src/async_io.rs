@@ -0,0 +1,114 @@
+//! Async Hjson Input and Output
+//!
+//! This module provides for reading and writing Hjson with `futures::io::AsyncRead` and
+//! `futures::io::AsyncWrite`. It is only available with the `async` feature.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures::Stream;
+use serde::{de, ser};
+
+use super::de::{from_slice, Progress, PushDeserializer};
+use super::error::{Error, Result};
+use super::ser::to_vec;
+
+/// Decodes a Hjson value from a `futures::io::AsyncRead`.
+pub async fn from_async_reader<R, T>(mut rdr: R) -> Result<T>
+where
+    R: AsyncRead + Unpin,
+    T: de::DeserializeOwned,
+{
+    let mut buf = Vec::new();
+    rdr.read_to_end(&mut buf).await?;
+    from_slice(&buf)
+}
+
+/// Encode the specified struct into a Hjson `futures::io::AsyncWrite`.
+pub async fn to_async_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: ser::Serialize,
+{
+    let buf = to_vec(value)?;
+    writer.write_all(&buf).await?;
+    Ok(())
+}
+
+/// Stream that deserializes multiple Hjson values from a `futures::io::AsyncRead`.
+///
+/// Values are returned as soon as they are complete, without waiting for the end of the input.
+/// A value that does not match the type is returned as an error and the stream goes on with
+/// the next value, a syntax error is the last item of the stream.
+pub struct AsyncStreamDeserializer<R, T>
+where
+    T: de::DeserializeOwned,
+{
+    rdr: R,
+    de: PushDeserializer<T>,
+    chunk: Vec<u8>,
+    failed: bool,
+}
+
+impl<R, T> AsyncStreamDeserializer<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: de::DeserializeOwned,
+{
+    /// Returns a `Stream` of decoded Hjson values from a `futures::io::AsyncRead`.
+    pub fn new(rdr: R) -> AsyncStreamDeserializer<R, T> {
+        AsyncStreamDeserializer {
+            rdr,
+            de: PushDeserializer::new(),
+            chunk: vec![0; 8192],
+            failed: false,
+        }
+    }
+
+    /// Unwrap the `AsyncRead` from the `AsyncStreamDeserializer`.
+    pub fn into_inner(self) -> R {
+        self.rdr
+    }
+}
+
+impl<R, T> Stream for AsyncStreamDeserializer<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: de::DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<T>>> {
+        let this = self.get_mut();
+        if this.failed {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match this.de.next_value() {
+                Ok(Progress::Value(v)) => return Poll::Ready(Some(Ok(v))),
+                Ok(Progress::Done) => return Poll::Ready(None),
+                Ok(Progress::NeedMoreData) => {}
+                Err(e) => {
+                    // a value that does not match the type is skipped, the
+                    // stream cannot continue after a syntax error
+                    this.failed = !matches!(e, Error::Data(..));
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+
+            match Pin::new(&mut this.rdr).poll_read(cx, &mut this.chunk) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(0)) => this.de.finish(),
+                Poll::Ready(Ok(n)) => this.de.feed(&this.chunk[..n]),
+                Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::Interrupted => {}
+                Poll::Ready(Err(e)) => {
+                    this.failed = true;
+                    return Poll::Ready(Some(Err(Error::Io(e))));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! ANSI colored output for terminals.
+//!
+//! `ColorFormatter` wraps the formatter for Hjson or JSON output and highlights what it
+//! writes with the colors of a `ColorTheme`:
+//!
+//! ```rust
+//! use serde_hjson::color::{ColorFormatter, ColorTheme};
+//! use serde_hjson::{HjsonFormatter, Serializer};
+//!
+//! let value: serde_hjson::Value = serde_hjson::from_str("{port: 8080}").unwrap();
+//! let theme = ColorTheme::new().key("1;34");
+//! let mut out = Vec::new();
+//! let formatter = ColorFormatter::with_theme(HjsonFormatter::new(), theme);
+//! let mut ser = Serializer::with_formatter(&mut out, formatter);
+//! serde::Serialize::serialize(&value, &mut ser).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(out).unwrap(),
+//!     "\x1b[90m{\x1b[0m\n  \x1b[1;34mport\x1b[0m\x1b[90m:\x1b[0m \x1b[36m8080\x1b[0m\n\x1b[90m}\x1b[0m"
+//! );
+//! ```
+//!
+//! The escape sequences only surround the text, so the indentation and line breaks are the
+//! same as without colors. The colored output is meant for reading, it is not parsed back.
+
+use std::borrow::Cow;
+use std::io;
+
+use super::error::Result;
+use super::ser::{FormatOptions, Formatter, HjsonFormatter};
+
+/// The colors of the syntax elements, as the parameters of an ANSI `SGR` escape sequence
+/// like `"32"` for green or `"1;34"` for bold blue. An empty style leaves the element
+/// uncolored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorTheme {
+    key: Cow<'static, str>,
+    string: Cow<'static, str>,
+    number: Cow<'static, str>,
+    boolean: Cow<'static, str>,
+    null: Cow<'static, str>,
+    punctuation: Cow<'static, str>,
+    comment: Cow<'static, str>,
+}
+
+impl ColorTheme {
+    /// Creates the default theme: blue keys, green strings, cyan numbers, yellow booleans,
+    /// magenta `null`, gray punctuation and dim comments.
+    pub fn new() -> Self {
+        ColorTheme {
+            key: Cow::Borrowed("34"),
+            string: Cow::Borrowed("32"),
+            number: Cow::Borrowed("36"),
+            boolean: Cow::Borrowed("33"),
+            null: Cow::Borrowed("35"),
+            punctuation: Cow::Borrowed("90"),
+            comment: Cow::Borrowed("2"),
+        }
+    }
+
+    /// Creates a theme that colors nothing.
+    pub fn plain() -> Self {
+        ColorTheme {
+            key: Cow::Borrowed(""),
+            string: Cow::Borrowed(""),
+            number: Cow::Borrowed(""),
+            boolean: Cow::Borrowed(""),
+            null: Cow::Borrowed(""),
+            punctuation: Cow::Borrowed(""),
+            comment: Cow::Borrowed(""),
+        }
+    }
+
+    /// Sets the style of object keys.
+    pub fn key(mut self, style: &str) -> Self {
+        self.key = Cow::Owned(style.to_owned());
+        self
+    }
+
+    /// Sets the style of strings.
+    pub fn string(mut self, style: &str) -> Self {
+        self.string = Cow::Owned(style.to_owned());
+        self
+    }
+
+    /// Sets the style of numbers.
+    pub fn number(mut self, style: &str) -> Self {
+        self.number = Cow::Owned(style.to_owned());
+        self
+    }
+
+    /// Sets the style of `true` and `false`.
+    pub fn boolean(mut self, style: &str) -> Self {
+        self.boolean = Cow::Owned(style.to_owned());
+        self
+    }
+
+    /// Sets the style of `null`.
+    pub fn null(mut self, style: &str) -> Self {
+        self.null = Cow::Owned(style.to_owned());
+        self
+    }
+
+    /// Sets the style of brackets, braces, commas and colons.
+    pub fn punctuation(mut self, style: &str) -> Self {
+        self.punctuation = Cow::Owned(style.to_owned());
+        self
+    }
+
+    /// Sets the style of comments.
+    pub fn comment(mut self, style: &str) -> Self {
+        self.comment = Cow::Owned(style.to_owned());
+        self
+    }
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        ColorTheme::new()
+    }
+}
+
+/// A formatter that writes the output of another formatter with ANSI colors.
+pub struct ColorFormatter<F = HjsonFormatter> {
+    inner: F,
+    theme: ColorTheme,
+    // reused for the output of the inner formatter
+    buf: Vec<u8>,
+}
+
+impl<F> ColorFormatter<F>
+where
+    F: Formatter,
+{
+    /// Construct a formatter that colors the output of `inner` with the default theme.
+    pub fn new(inner: F) -> Self {
+        ColorFormatter::with_theme(inner, ColorTheme::new())
+    }
+
+    /// Construct a formatter that colors the output of `inner` with the given theme.
+    pub fn with_theme(inner: F, theme: ColorTheme) -> Self {
+        ColorFormatter {
+            inner,
+            theme,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns the wrapped formatter.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    // Writes what `write` makes the inner formatter write, with the text between the
+    // leading and trailing whitespace in the given style.
+    fn paint<W, G>(&mut self, writer: &mut W, style: Style, write: G) -> Result<()>
+    where
+        W: io::Write,
+        G: FnOnce(&mut F, &mut Vec<u8>) -> Result<()>,
+    {
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.clear();
+        let result = write(&mut self.inner, &mut buf).and_then(|()| {
+            let style = match style {
+                Style::Key => &self.theme.key,
+                Style::String => &self.theme.string,
+                Style::Number => &self.theme.number,
+                Style::Boolean => &self.theme.boolean,
+                Style::Null => &self.theme.null,
+                Style::Punctuation => &self.theme.punctuation,
+                Style::Comment => &self.theme.comment,
+            };
+            write_styled(writer, style, &buf)
+        });
+        self.buf = buf;
+        result
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Style {
+    Key,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Punctuation,
+    Comment,
+}
+
+fn write_styled<W>(writer: &mut W, style: &str, text: &[u8]) -> Result<()>
+where
+    W: io::Write,
+{
+    let start = text
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(text.len());
+    let end = text
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |i| i + 1);
+    if style.is_empty() || start == end {
+        return writer.write_all(text).map_err(From::from);
+    }
+    writer.write_all(&text[..start])?;
+    write!(writer, "\x1b[{}m", style)?;
+    writer.write_all(&text[start..end])?;
+    writer.write_all(b"\x1b[0m")?;
+    writer.write_all(&text[end..]).map_err(From::from)
+}
+
+impl<F> Formatter for ColorFormatter<F>
+where
+    F: Formatter,
+{
+    fn open<W>(&mut self, writer: &mut W, ch: u8) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Punctuation, |inner, buf| inner.open(buf, ch))
+    }
+
+    fn comma<W>(&mut self, writer: &mut W, first: bool) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Punctuation, |inner, buf| {
+            inner.comma(buf, first)
+        })
+    }
+
+    fn colon<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Punctuation, |inner, buf| inner.colon(buf))
+    }
+
+    fn close<W>(&mut self, writer: &mut W, ch: u8) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Punctuation, |inner, buf| {
+            inner.close(buf, ch)
+        })
+    }
+
+    fn newline<W>(&mut self, writer: &mut W, add_indent: i32) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.inner.newline(writer, add_indent)
+    }
+
+    fn start_value<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.inner.start_value(writer)
+    }
+
+    fn options(&self) -> &FormatOptions {
+        self.inner.options()
+    }
+
+    fn write_key<W>(&mut self, writer: &mut W, path: &str, key: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Key, |inner, buf| {
+            inner.write_key(buf, path, key)
+        })
+    }
+
+    fn begin_object_value<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.inner.begin_object_value(writer, path)
+    }
+
+    fn end_object_value<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.inner.end_object_value(writer, path)
+    }
+
+    fn begin_inline<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.inner.begin_inline(writer, path)
+    }
+
+    fn end_inline<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.inner.end_inline(writer, path)
+    }
+
+    fn write_string<W>(&mut self, writer: &mut W, path: &str, value: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::String, |inner, buf| {
+            inner.write_string(buf, path, value)
+        })
+    }
+
+    fn write_number<W>(&mut self, writer: &mut W, path: &str, value: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Number, |inner, buf| {
+            inner.write_number(buf, path, value)
+        })
+    }
+
+    fn write_bool<W>(&mut self, writer: &mut W, path: &str, value: bool) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Boolean, |inner, buf| {
+            inner.write_bool(buf, path, value)
+        })
+    }
+
+    fn write_null<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Null, |inner, buf| {
+            inner.write_null(buf, path)
+        })
+    }
+
+    fn write_empty<W>(&mut self, writer: &mut W, path: &str, ch: u8) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Punctuation, |inner, buf| {
+            inner.write_empty(buf, path, ch)
+        })
+    }
+
+    fn write_comment<W>(
+        &mut self,
+        writer: &mut W,
+        path: &str,
+        comment: &str,
+        trailing: bool,
+    ) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.paint(writer, Style::Comment, |inner, buf| {
+            inner.write_comment(buf, path, comment, trailing)
+        })
+    }
+}
@@ -0,0 +1,137 @@
+//! Values with comments.
+
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+
+use serde::{de, ser};
+
+/// The newtype struct name that `ser::Serializer` recognizes as a commented value.
+pub(crate) const NAME: &str = "$serde_hjson::private::Commented";
+
+/// The comments of the value being serialized.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Comments {
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+thread_local! {
+    // Set by `Commented::serialize` while the inner value is serialized, since the
+    // comments can not be passed through the `Serializer` trait.
+    static PENDING: RefCell<Option<Comments>> = const { RefCell::new(None) };
+}
+
+/// Takes the comments of the commented value that is being serialized.
+pub(crate) fn take_pending() -> Option<Comments> {
+    PENDING.with(|pending| pending.borrow_mut().take())
+}
+
+/// A value that is written with comments by the Hjson serializer.
+///
+/// The `comment` is written as `#` lines above the key of an object member, or above the
+/// value itself in arrays and at the root. The `trailing` comment is written after the value
+/// on the same line. Other serializers, including the one for `Value`, only see the inner
+/// value, and deserializing reads the inner value without comments.
+///
+/// ```rust
+/// use serde_hjson::Commented;
+/// use std::collections::BTreeMap;
+///
+/// let mut ports = BTreeMap::new();
+/// ports.insert("http", Commented::new(80, "plain text"));
+/// ports.insert("https", Commented::trailing(443, "with TLS"));
+/// assert_eq!(
+///     serde_hjson::to_string(&ports).unwrap(),
+///     "{\n  # plain text\n  http: 80\n  https: 443 # with TLS\n}"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Commented<T> {
+    /// The value.
+    pub value: T,
+    /// The comment written above the value. Each line becomes a `#` comment.
+    pub comment: Option<String>,
+    /// The comment written after the value on the same line.
+    pub trailing: Option<String>,
+}
+
+impl<T> Commented<T> {
+    /// Wraps a value with a comment that is written above it.
+    pub fn new(value: T, comment: &str) -> Self {
+        Commented {
+            value,
+            comment: Some(comment.to_owned()),
+            trailing: None,
+        }
+    }
+
+    /// Wraps a value with a comment that is written after it on the same line.
+    pub fn trailing(value: T, comment: &str) -> Self {
+        Commented {
+            value,
+            comment: None,
+            trailing: Some(comment.to_owned()),
+        }
+    }
+
+    /// Returns the inner value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> From<T> for Commented<T> {
+    fn from(value: T) -> Self {
+        Commented {
+            value,
+            comment: None,
+            trailing: None,
+        }
+    }
+}
+
+impl<T> Deref for Commented<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Commented<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> ser::Serialize for Commented<T>
+where
+    T: ser::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        PENDING.with(|pending| {
+            *pending.borrow_mut() = Some(Comments {
+                before: self.comment.clone(),
+                after: self.trailing.clone(),
+            })
+        });
+        let result = serializer.serialize_newtype_struct(NAME, &self.value);
+        take_pending();
+        result
+    }
+}
+
+impl<'de, T> de::Deserialize<'de> for Commented<T>
+where
+    T: de::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(From::from)
+    }
+}
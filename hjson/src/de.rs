@@ -2,7 +2,6 @@
 //!
 //! This module provides for Hjson deserialization with the type `Deserializer`.
 
-use std::char;
 use std::io;
 use std::marker::PhantomData;
 use std::str;
@@ -10,51 +9,48 @@ use std::str;
 use serde::de;
 
 use super::error::{Error, ErrorCode, Result};
-use super::util::StringReader;
-use super::util::{Number, ParseNumber};
-
-enum State {
-    Normal,
-    Root,
-    Keyname,
-}
+use super::reader::{Checkpoint, Event, Input, Number, Reader, SliceInput, Span};
+use super::ser::BytesFormat;
+use super::util::{decode_bytes, parse_index, MapKeyDeserializer};
 
 /// A structure that deserializes Hjson into Rust values.
-pub struct Deserializer<Iter: Iterator<Item = u8>> {
-    rdr: StringReader<Iter>,
-    str_buf: Vec<u8>,
-    state: State,
+pub struct Deserializer<Iter: Input> {
+    rdr: Reader<Iter>,
+    bytes: BytesFormat,
 }
 
-// macro_rules! try_or_invalid {
-//     ($self_:expr, $e:expr) => {
-//         match $e {
-//             Some(v) => v,
-//             None => { return Err($self_.error(ErrorCode::InvalidNumber)); }
-//         }
-//     }
-// }
-
 impl<Iter> Deserializer<Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
 {
-    /// Creates the Hjson parser from an `std::iter::Iterator`.
+    /// Creates the Hjson parser from an `Input`, like an `std::iter::Iterator` or a
+    /// `reader::SliceInput`.
     #[inline]
     pub fn new(rdr: Iter) -> Deserializer<Iter> {
-        Deserializer {
-            rdr: StringReader::new(rdr),
-            str_buf: Vec::with_capacity(128),
-            state: State::Normal,
-        }
+        Deserializer::from_reader(Reader::new(rdr))
     }
 
-    /// Creates the Hjson parser from an `std::iter::Iterator`.
+    /// Creates the Hjson parser for a root object without braces from an `Input`.
     #[inline]
     pub fn new_for_root(rdr: Iter) -> Deserializer<Iter> {
-        let mut res = Deserializer::new(rdr);
-        res.state = State::Root;
-        res
+        Deserializer::from_reader(Reader::new_for_root(rdr))
+    }
+
+    /// Creates the Hjson parser from a `reader::Reader`.
+    #[inline]
+    pub fn from_reader(mut rdr: Reader<Iter>) -> Deserializer<Iter> {
+        rdr.set_comments(false);
+        Deserializer {
+            rdr,
+            bytes: BytesFormat::Array,
+        }
+    }
+
+    /// Sets how strings are decoded into byte buffers, like `serde_bytes::ByteBuf`. With the
+    /// default `BytesFormat::Array` a string gives its UTF-8 bytes, with `BytesFormat::Base64`
+    /// or `BytesFormat::Hex` it is decoded. Arrays of numbers are always accepted.
+    pub fn set_bytes_format(&mut self, format: BytesFormat) {
+        self.bytes = format;
     }
 
     /// The `Deserializer::end` method should be called after a value has been fully deserialized.
@@ -62,447 +58,65 @@ where
     /// only has trailing whitespace.
     #[inline]
     pub fn end(&mut self) -> Result<()> {
-        self.rdr.parse_whitespace()?;
-        if self.rdr.eof()? {
-            Ok(())
-        } else {
-            Err(self.rdr.error(ErrorCode::TrailingCharacters))
-        }
+        self.rdr.end()
     }
 
-    fn is_punctuator_char(ch: u8) -> bool {
-        match ch {
-            b'{' | b'}' | b'[' | b']' | b',' | b':' => true,
-            _ => false,
-        }
+    /// Returns the number of bytes consumed so far.
+    pub fn byte_offset(&self) -> usize {
+        self.rdr.offset()
     }
 
-    fn parse_keyname<'de, V>(&mut self, visitor: V) -> Result<V::Value>
+    // Deserializes the next value and gives the data errors raised for it the position
+    // of the value, including those of `Deserialize` impls that validate the value
+    // after reading it.
+    fn read_value<'de, T>(&mut self, seed: T) -> Result<T::Value>
     where
-        V: de::Visitor<'de>,
+        T: de::DeserializeSeed<'de>,
     {
-        // quotes for keys are optional in Hjson
-        // unless they include {}[],: or whitespace.
-        // assume whitespace was already eaten
-
-        self.str_buf.clear();
-
-        let mut space: Option<usize> = None;
-        loop {
-            let ch = self.rdr.next_char_or_null()?;
-
-            if ch == b':' {
-                if self.str_buf.is_empty() {
-                    return Err(self.rdr.error(ErrorCode::Custom(
-                        "Found ':' but no key name (for an empty key name use quotes)".to_string(),
-                    )));
-                } else if space.is_some() && space.unwrap() != self.str_buf.len() {
-                    return Err(self.rdr.error(ErrorCode::Custom(
-                        "Found whitespace in your key name (use quotes to include)".to_string(),
-                    )));
-                }
-                self.rdr.uneat_char(ch);
-                let s = str::from_utf8(&self.str_buf).unwrap();
-                return visitor.visit_str(s);
-            } else if ch <= b' ' {
-                if ch == 0 {
-                    return Err(self.rdr.error(ErrorCode::EOFWhileParsingObject));
-                } else if space.is_none() {
-                    space = Some(self.str_buf.len());
-                }
-            } else if Self::is_punctuator_char(ch) {
-                return Err(self.rdr.error(ErrorCode::Custom("Found a punctuator where a key name was expected (check your syntax or use quotes if the key name includes {}[],: or whitespace)".to_string())));
-            } else {
-                self.str_buf.push(ch);
-            }
-        }
+        let span = match self.rdr.peek_spanned()? {
+            Some((_, span)) => span,
+            None => Span::default(),
+        };
+        seed.deserialize(&mut *self)
+            .map_err(|err| err.at(span.line, span.column))
     }
 
     fn parse_value<'de, V>(&mut self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.rdr.parse_whitespace()?;
-
-        if self.rdr.eof()? {
+        let Some((event, span)) = self.rdr.next_spanned()? else {
             return Err(self.rdr.error(ErrorCode::EOFWhileParsingValue));
-        }
-
-        match self.state {
-            State::Keyname => {
-                self.state = State::Normal;
-                return self.parse_keyname(visitor);
-            }
-            State::Root => {
-                self.state = State::Normal;
-                return self.visit_map(true, visitor);
-            }
-            State::Normal => {}
-        }
-
-        match self.rdr.peek_or_null()? {
-            /*
-            b'-' => {
-                self.rdr.eat_char();
-                self.parse_integer(false, visitor)
-            }
-            b'0' ... b'9' => {
-                self.parse_integer(true, visitor)
-            }
-            */
-            b'"' => {
-                self.rdr.eat_char();
-                self.parse_string()?;
-                let s = str::from_utf8(&self.str_buf).unwrap();
-                visitor.visit_str(s)
-            }
-            b'[' => {
-                self.rdr.eat_char();
-                let ret = visitor.visit_seq(SeqVisitor::new(self))?;
-                self.rdr.parse_whitespace()?;
-                match self.rdr.next_char()? {
-                    Some(b']') => Ok(ret),
-                    Some(_) => Err(self.rdr.error(ErrorCode::TrailingCharacters)),
-                    None => Err(self.rdr.error(ErrorCode::EOFWhileParsingList)),
-                }
-            }
-            b'{' => {
-                self.rdr.eat_char();
-                self.visit_map(false, visitor)
-            }
-            b'\x00' => Err(self.rdr.error(ErrorCode::ExpectedSomeValue)),
-            _ => self.parse_tfnns(visitor),
-        }
-    }
-
-    fn visit_map<'de, V>(&mut self, root: bool, visitor: V) -> Result<V::Value>
-    where
-        V: de::Visitor<'de>,
-    {
-        let ret = visitor.visit_map(MapVisitor::new(self, root))?;
-        self.rdr.parse_whitespace()?;
-        match self.rdr.next_char()? {
-            Some(b'}') => {
-                if !root {
-                    Ok(ret)
-                } else {
-                    Err(self.rdr.error(ErrorCode::TrailingCharacters))
-                } // todo
-            }
-            Some(_) => Err(self.rdr.error(ErrorCode::TrailingCharacters)),
-            None => {
-                if root {
-                    Ok(ret)
-                } else {
-                    Err(self.rdr.error(ErrorCode::EOFWhileParsingObject))
-                }
-            }
-        }
-    }
-
-    fn parse_ident(&mut self, ident: &[u8]) -> Result<()> {
-        for c in ident {
-            if Some(*c) != self.rdr.next_char()? {
-                return Err(self.rdr.error(ErrorCode::ExpectedSomeIdent));
-            }
-        }
-
-        Ok(())
-    }
-
-    fn parse_tfnns<'de, V>(&mut self, visitor: V) -> Result<V::Value>
-    where
-        V: de::Visitor<'de>,
-    {
-        // Hjson strings can be quoteless
-        // returns string, true, false, or null.
-        self.str_buf.clear();
-
-        let first = self.rdr.peek()?.unwrap();
-
-        if Self::is_punctuator_char(first) {
-            return Err(self.rdr.error(ErrorCode::PunctuatorInQlString));
-        }
-
-        loop {
-            let ch = self.rdr.next_char_or_null()?;
-
-            let is_eol = ch == b'\r' || ch == b'\n' || ch == b'\x00';
-            let is_comment = ch == b'#'
-                || ch == b'/' && {
-                    let next = self.rdr.peek_or_null()?;
-                    next == b'/' || next == b'*'
-                };
-            if is_eol || is_comment || ch == b',' || ch == b'}' || ch == b']' {
-                let chf = self.str_buf[0];
-                match chf {
-                    b'f' => {
-                        if str::from_utf8(&self.str_buf).unwrap().trim() == "false" {
-                            self.rdr.uneat_char(ch);
-                            return visitor.visit_bool(false);
-                        }
-                    }
-                    b'n' => {
-                        if str::from_utf8(&self.str_buf).unwrap().trim() == "null" {
-                            self.rdr.uneat_char(ch);
-                            return visitor.visit_unit();
-                        }
-                    }
-                    b't' => {
-                        if str::from_utf8(&self.str_buf).unwrap().trim() == "true" {
-                            self.rdr.uneat_char(ch);
-                            return visitor.visit_bool(true);
-                        }
-                    }
-                    _ => {
-                        if chf == b'-' || chf.is_ascii_digit() {
-                            let mut pn = ParseNumber::new(self.str_buf.iter().copied());
-                            match pn.parse(false) {
-                                Ok(Number::F64(v)) => {
-                                    self.rdr.uneat_char(ch);
-                                    return visitor.visit_f64(v);
-                                }
-                                Ok(Number::U64(v)) => {
-                                    self.rdr.uneat_char(ch);
-                                    return visitor.visit_u64(v);
-                                }
-                                Ok(Number::I64(v)) => {
-                                    self.rdr.uneat_char(ch);
-                                    return visitor.visit_i64(v);
-                                }
-                                Err(_) => {} // not a number, continue
-                            }
-                        }
-                    }
-                }
-                if is_eol {
-                    let pos = self.rdr.pos();
-                    // remove any whitespace at the end (ignored in quoteless strings)
-                    return visitor.visit_str(
-                        str::from_utf8(&self.str_buf)
-                            .map_err(|_| {
-                                Error::Syntax(ErrorCode::EOFWhileParsingString, pos.0, pos.1)
-                            })?
-                            .trim(),
-                    );
-                }
-            }
-            self.str_buf.push(ch);
-
-            if self.str_buf == b"'''" {
-                return self.parse_ml_string(visitor);
-            }
-        }
-    }
-
-    fn decode_hex_escape(&mut self) -> Result<u16> {
-        let mut i = 0;
-        let mut n = 0u16;
-        while i < 4 && !self.rdr.eof()? {
-            n = match self.rdr.next_char_or_null()? {
-                c @ b'0'..=b'9' => n * 16_u16 + ((c as u16) - (b'0' as u16)),
-                b'a' | b'A' => n * 16_u16 + 10_u16,
-                b'b' | b'B' => n * 16_u16 + 11_u16,
-                b'c' | b'C' => n * 16_u16 + 12_u16,
-                b'd' | b'D' => n * 16_u16 + 13_u16,
-                b'e' | b'E' => n * 16_u16 + 14_u16,
-                b'f' | b'F' => n * 16_u16 + 15_u16,
-                _ => {
-                    return Err(self.rdr.error(ErrorCode::InvalidEscape));
-                }
-            };
-
-            i += 1;
-        }
-
-        // Error out if we didn't parse 4 digits.
-        if i != 4 {
-            return Err(self.rdr.error(ErrorCode::InvalidEscape));
-        }
-
-        Ok(n)
-    }
-
-    fn ml_skip_white(&mut self) -> Result<bool> {
-        match self.rdr.peek_or_null()? {
-            b' ' | b'\t' | b'\r' => {
-                self.rdr.eat_char();
-                Ok(true)
-            }
-            _ => Ok(false),
-        }
-    }
-
-    fn ml_skip_indent(&mut self, indent: usize) -> Result<()> {
-        let mut skip = indent;
-        while self.ml_skip_white()? && skip > 0 {
-            skip -= 1;
-        }
-        Ok(())
-    }
-
-    fn parse_ml_string<'de, V>(&mut self, visitor: V) -> Result<V::Value>
-    where
-        V: de::Visitor<'de>,
-    {
-        self.str_buf.clear();
-
-        // Parse a multiline string value.
-        let mut triple = 0;
-
-        // we are at ''' +1 - get indent
-        let (_, col) = self.rdr.pos();
-
-        // Fallback machanism if col is improper
-        let indent = if col >= 4 { col - 4 } else { 0 };
-
-        // skip white/to (newline)
-        while self.ml_skip_white()? {}
-        if self.rdr.peek_or_null()? == b'\n' {
-            self.rdr.eat_char();
-            self.ml_skip_indent(indent)?;
-        }
-
-        // When parsing multiline string values, we must look for ' characters.
-        loop {
-            if self.rdr.eof()? {
-                return Err(self.rdr.error(ErrorCode::EOFWhileParsingString));
-            } // todo error("Bad multiline string");
-            let ch = self.rdr.next_char_or_null()?;
-
-            if ch == b'\'' {
-                triple += 1;
-                if triple == 3 {
-                    if self.str_buf.last() == Some(&b'\n') {
-                        self.str_buf.pop();
-                    }
-                    let res = str::from_utf8(&self.str_buf).unwrap();
-                    //todo if (self.str_buf.slice(-1) === '\n') self.str_buf=self.str_buf.slice(0, -1); // remove last EOL
-                    return visitor.visit_str(res);
-                } else {
-                    continue;
-                }
-            }
-
-            while triple > 0 {
-                self.str_buf.push(b'\'');
-                triple -= 1;
-            }
-
-            if ch != b'\r' {
-                self.str_buf.push(ch);
-            }
-            if ch == b'\n' {
-                self.ml_skip_indent(indent)?;
-            }
-        }
-    }
-
-    fn parse_string(&mut self) -> Result<()> {
-        self.str_buf.clear();
-
-        loop {
-            let Some(ch) = self.rdr.next_char()? else {
-                return Err(self.rdr.error(ErrorCode::EOFWhileParsingString));
-            };
-
-            match ch {
-                b'"' => {
-                    return Ok(());
-                }
-                b'\\' => {
-                    let Some(ch) = self.rdr.next_char()? else {
-                        return Err(self.rdr.error(ErrorCode::EOFWhileParsingString));
-                    };
-
-                    match ch {
-                        b'"' => self.str_buf.push(b'"'),
-                        b'\\' => self.str_buf.push(b'\\'),
-                        b'/' => self.str_buf.push(b'/'),
-                        b'b' => self.str_buf.push(b'\x08'),
-                        b'f' => self.str_buf.push(b'\x0c'),
-                        b'n' => self.str_buf.push(b'\n'),
-                        b'r' => self.str_buf.push(b'\r'),
-                        b't' => self.str_buf.push(b'\t'),
-                        b'u' => {
-                            let c = match self.decode_hex_escape()? {
-                                0xDC00..=0xDFFF => {
-                                    return Err(self
-                                        .rdr
-                                        .error(ErrorCode::LoneLeadingSurrogateInHexEscape));
-                                }
-
-                                // Non-BMP characters are encoded as a sequence of
-                                // two hex escapes, representing UTF-16 surrogates.
-                                n1 @ 0xD800..=0xDBFF => {
-                                    match (self.rdr.next_char()?, self.rdr.next_char()?) {
-                                        (Some(b'\\'), Some(b'u')) => (),
-                                        _ => {
-                                            return Err(self
-                                                .rdr
-                                                .error(ErrorCode::UnexpectedEndOfHexEscape));
-                                        }
-                                    }
-
-                                    let n2 = self.decode_hex_escape()?;
-
-                                    if !(0xDC00..=0xDFFF).contains(&n2) {
-                                        return Err(self
-                                            .rdr
-                                            .error(ErrorCode::LoneLeadingSurrogateInHexEscape));
-                                    }
-
-                                    let n = (((n1 - 0xD800) as u32) << 10 | (n2 - 0xDC00) as u32)
-                                        + 0x1_0000;
-
-                                    match char::from_u32(n) {
-                                        Some(c) => c,
-                                        None => {
-                                            return Err(self
-                                                .rdr
-                                                .error(ErrorCode::InvalidUnicodeCodePoint));
-                                        }
-                                    }
-                                }
-
-                                n => match char::from_u32(n as u32) {
-                                    Some(c) => c,
-                                    None => {
-                                        return Err(self
-                                            .rdr
-                                            .error(ErrorCode::InvalidUnicodeCodePoint));
-                                    }
-                                },
-                            };
-
-                            self.str_buf.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
-                        }
-                        _ => {
-                            return Err(self.rdr.error(ErrorCode::InvalidEscape));
-                        }
-                    }
-                }
-                ch => {
-                    self.str_buf.push(ch);
-                }
-            }
-        }
+        };
+        let result = match event {
+            Event::Key(s) | Event::String(s) => visitor.visit_str(s),
+            Event::Number(Number::F64(v)) => visitor.visit_f64(v),
+            Event::Number(Number::U64(v)) => visitor.visit_u64(v),
+            Event::Number(Number::I64(v)) => visitor.visit_i64(v),
+            Event::Bool(v) => visitor.visit_bool(v),
+            Event::Null => visitor.visit_unit(),
+            Event::StartArray => visitor
+                .visit_seq(SeqVisitor::new(self))
+                .and_then(|ret| self.parse_end(Event::EndArray, ret)),
+            Event::StartObject => visitor
+                .visit_map(MapVisitor::new(self))
+                .and_then(|ret| self.parse_end(Event::EndObject, ret)),
+            _ => return Err(self.rdr.error(ErrorCode::ExpectedSomeValue)),
+        };
+        result.map_err(|err| err.at(span.line, span.column))
     }
 
-    fn parse_object_colon(&mut self) -> Result<()> {
-        self.rdr.parse_whitespace()?;
-
-        match self.rdr.next_char()? {
-            Some(b':') => Ok(()),
-            Some(_) => Err(self.rdr.error(ErrorCode::ExpectedColon)),
-            None => Err(self.rdr.error(ErrorCode::EOFWhileParsingObject)),
+    fn parse_end<T>(&mut self, expected: Event<'static>, ret: T) -> Result<T> {
+        match self.rdr.next_event()? {
+            Some(ref event) if *event == expected => Ok(ret),
+            _ => Err(self.rdr.error(ErrorCode::TrailingCharacters)),
         }
     }
 }
 
-impl<'de, 'a, Iter> de::Deserializer<'de> for &'a mut Deserializer<Iter>
+impl<'de, Iter> de::Deserializer<'de> for &mut Deserializer<Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
 {
     type Error = Error;
 
@@ -511,7 +125,6 @@ where
     where
         V: de::Visitor<'de>,
     {
-        if let State::Root = self.state {}
         self.parse_value(visitor)
     }
 
@@ -521,12 +134,9 @@ where
     where
         V: de::Visitor<'de>,
     {
-        self.rdr.parse_whitespace()?;
-
-        match self.rdr.peek_or_null()? {
-            b'n' => {
-                self.rdr.eat_char();
-                self.parse_ident(b"ull")?;
+        match self.rdr.peek_event()? {
+            Some(Event::Null) => {
+                self.rdr.next_event()?;
                 visitor.visit_none()
             }
             _ => visitor.visit_some(self),
@@ -542,26 +152,63 @@ where
         visitor.visit_newtype_struct(self)
     }
 
+    /// Decodes a string in the format set with `set_bytes_format`, and reads any other value
+    /// like `deserialize_any`.
+    #[inline]
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.bytes == BytesFormat::Array {
+            return self.parse_value(visitor);
+        }
+        if let Some(Event::String(_)) = self.rdr.peek_event()? {
+            let bytes = match self.rdr.next_event()? {
+                Some(Event::String(s)) => decode_bytes(s, self.bytes)?,
+                _ => return Err(self.rdr.error(ErrorCode::ExpectedSomeValue)),
+            };
+            return visitor.visit_byte_buf(bytes);
+        }
+        self.parse_value(visitor)
+    }
+
+    /// Skips the value without decoding or copying its strings.
+    #[inline]
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.rdr.skip_value()?;
+        visitor.visit_unit()
+    }
+
     serde::forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf unit unit_struct seq tuple map
-        tuple_struct struct enum identifier ignored_any
+        unit unit_struct seq tuple map tuple_struct struct enum identifier
     }
 }
 
-struct SeqVisitor<'a, Iter: 'a + Iterator<Item = u8>> {
+struct SeqVisitor<'a, Iter: 'a + Input> {
     de: &'a mut Deserializer<Iter>,
+    index: usize,
 }
 
-impl<'a, Iter: Iterator<Item = u8>> SeqVisitor<'a, Iter> {
+impl<'a, Iter: Input> SeqVisitor<'a, Iter> {
     fn new(de: &'a mut Deserializer<Iter>) -> Self {
-        Self { de }
+        Self { de, index: 0 }
     }
 }
 
 impl<'de, 'a, Iter> de::SeqAccess<'de> for SeqVisitor<'a, Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
 {
     type Error = Error;
 
@@ -569,50 +216,37 @@ where
     where
         T: de::DeserializeSeed<'de>,
     {
-        self.de.rdr.parse_whitespace()?;
-
-        match self.de.rdr.peek()? {
-            Some(b']') => {
-                return Ok(None);
-            }
-            Some(_) => {}
-            None => {
-                return Err(self.de.rdr.error(ErrorCode::EOFWhileParsingList));
-            }
-        }
-
-        let value = seed.deserialize(&mut *self.de)?;
-
-        // in Hjson the comma is optional and trailing commas are allowed
-        self.de.rdr.parse_whitespace()?;
-        if self.de.rdr.peek()? == Some(b',') {
-            self.de.rdr.eat_char();
-            self.de.rdr.parse_whitespace()?;
+        if let Some(Event::EndArray) = self.de.rdr.peek_event()? {
+            return Ok(None);
         }
 
+        let value = self
+            .de
+            .read_value(seed)
+            .map_err(|err| err.in_member(&self.index.to_string()))?;
+        self.index += 1;
         Ok(Some(value))
     }
 }
 
-struct MapVisitor<'a, Iter: 'a + Iterator<Item = u8>> {
+struct MapVisitor<'a, Iter: 'a + Input> {
     de: &'a mut Deserializer<Iter>,
-    first: bool,
-    root: bool,
+    // the key of the current member, for the path of errors in its value
+    key: String,
 }
 
-impl<'a, Iter: Iterator<Item = u8>> MapVisitor<'a, Iter> {
-    fn new(de: &'a mut Deserializer<Iter>, root: bool) -> Self {
+impl<'a, Iter: Input> MapVisitor<'a, Iter> {
+    fn new(de: &'a mut Deserializer<Iter>) -> Self {
         Self {
             de,
-            first: true,
-            root,
+            key: String::new(),
         }
     }
 }
 
 impl<'de, 'a, Iter> de::MapAccess<'de> for MapVisitor<'a, Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
 {
     type Error = Error;
 
@@ -620,38 +254,20 @@ where
     where
         K: de::DeserializeSeed<'de>,
     {
-        self.de.rdr.parse_whitespace()?;
-
-        if self.first {
-            self.first = false;
-        } else if self.de.rdr.peek()? == Some(b',') {
-            // in Hjson the comma is optional and trailing commas are allowed
-            self.de.rdr.eat_char();
-            self.de.rdr.parse_whitespace()?;
-        }
-
-        match self.de.rdr.peek()? {
-            Some(b'}') => return Ok(None), // handled later for root
-            Some(_) => {}
-            None => {
-                if self.root {
-                    return Ok(None);
-                } else {
-                    return Err(self.de.rdr.error(ErrorCode::EOFWhileParsingObject));
-                }
-            }
+        if let Some(Event::EndObject) = self.de.rdr.peek_event()? {
+            return Ok(None);
         }
 
-        match self.de.rdr.peek()? {
-            Some(ch) => {
-                self.de.state = if ch == b'"' {
-                    State::Normal
-                } else {
-                    State::Keyname
-                };
-                Ok(Some(seed.deserialize(&mut *self.de)?))
+        match self.de.rdr.next_spanned()? {
+            Some((Event::Key(key), span)) => {
+                self.key.clear();
+                self.key.push_str(key);
+                let key = seed
+                    .deserialize(MapKeyDeserializer::new(key))
+                    .map_err(|err| err.in_member(&self.key).at(span.line, span.column))?;
+                Ok(Some(key))
             }
-            None => Err(self.de.rdr.error(ErrorCode::EOFWhileParsingValue)),
+            _ => Err(self.de.rdr.error(ErrorCode::KeyMustBeAString)),
         }
     }
 
@@ -659,15 +275,15 @@ where
     where
         V: de::DeserializeSeed<'de>,
     {
-        self.de.parse_object_colon()?;
-
-        seed.deserialize(&mut *self.de)
+        self.de
+            .read_value(seed)
+            .map_err(|err| err.in_member(&self.key))
     }
 }
 
-impl<'de, 'a, Iter> de::VariantAccess<'de> for &'a mut Deserializer<Iter>
+impl<'de, Iter> de::VariantAccess<'de> for &mut Deserializer<Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
 {
     type Error = Error;
 
@@ -702,7 +318,7 @@ where
 /// Iterator that deserializes a stream into multiple Hjson values.
 pub struct StreamDeserializer<T, Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
     T: de::DeserializeOwned,
 {
     deser: Deserializer<Iter>,
@@ -711,7 +327,7 @@ where
 
 impl<T, Iter> StreamDeserializer<T, Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
     T: de::DeserializeOwned,
 {
     /// Returns an `Iterator` of decoded Hjson values from an iterator over
@@ -722,11 +338,16 @@ where
             _marker: PhantomData,
         }
     }
+
+    /// Returns the number of bytes consumed so far.
+    pub fn byte_offset(&self) -> usize {
+        self.deser.rdr.offset()
+    }
 }
 
 impl<T, Iter> Iterator for StreamDeserializer<T, Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
     T: de::DeserializeOwned,
 {
     type Item = Result<T>;
@@ -735,13 +356,9 @@ where
         // skip whitespaces, if any
         // this helps with trailing whitespaces, since whitespaces between
         // values are handled for us.
-        if let Err(e) = self.deser.rdr.parse_whitespace() {
-            return Some(Err(e));
-        };
-
-        match self.deser.rdr.eof() {
-            Ok(true) => None,
-            Ok(false) => match de::Deserialize::deserialize(&mut self.deser) {
+        match self.deser.rdr.peek_event() {
+            Ok(None) => None,
+            Ok(Some(_)) => match self.deser.read_value(PhantomData) {
                 Ok(v) => Some(Ok(v)),
                 Err(e) => Some(Err(e)),
             },
@@ -752,6 +369,390 @@ where
 
 //////////////////////////////////////////////////////////////////////////////
 
+/// How a `DocumentStream` finds the end of a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Separator {
+    /// Documents follow each other directly, like in a `StreamDeserializer`. Each document
+    /// must be a single value, so a root object needs braces.
+    None,
+    /// Documents are separated by one or more blank lines.
+    BlankLine,
+    /// Documents are separated by a line that only consists of the given text, e.g. `---`.
+    Line(String),
+}
+
+/// A value read by a `DocumentStream` and where it was found in the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document<T> {
+    /// The deserialized value.
+    pub value: T,
+    /// Byte offset where the document starts.
+    pub start: usize,
+    /// Byte offset just past the end of the document.
+    pub end: usize,
+    /// Line where the document starts, starting at 1.
+    pub line: usize,
+}
+
+/// Iterator that deserializes a sequence of Hjson documents from a byte slice and reports the
+/// location of each.
+///
+/// With a `Separator` other than `Separator::None` each document is read like `from_slice`
+/// reads its input, so documents can be root objects without braces. After a document fails
+/// to parse the error is returned and reading continues with the next document. Without a
+/// separator reading continues on the line after the error.
+///
+/// ```rust
+/// use serde_hjson::de::{DocumentStream, Separator};
+/// use serde_hjson::Value;
+///
+/// let input = b"level: info\nmsg: started\n---\nlevel: warn\nmsg: slow\n";
+/// let docs = DocumentStream::<Value>::with_separator(input, Separator::Line("---".into()));
+/// let lines: Vec<usize> = docs.map(|doc| doc.unwrap().line).collect();
+/// assert_eq!(lines, [1, 4]);
+/// ```
+pub struct DocumentStream<'a, T>
+where
+    T: de::DeserializeOwned,
+{
+    input: &'a [u8],
+    separator: Separator,
+    pos: usize,
+    line: usize,
+    col: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> DocumentStream<'a, T>
+where
+    T: de::DeserializeOwned,
+{
+    /// Returns an `Iterator` of documents that follow each other without a separator.
+    pub fn new(input: &'a [u8]) -> DocumentStream<'a, T> {
+        DocumentStream::with_separator(input, Separator::None)
+    }
+
+    /// Returns an `Iterator` of documents separated by `separator`.
+    pub fn with_separator(input: &'a [u8], separator: Separator) -> DocumentStream<'a, T> {
+        DocumentStream {
+            input,
+            separator,
+            pos: 0,
+            line: 1,
+            col: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the byte offset where reading continues. It can be passed to `seek` to resume
+    /// reading later.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// Continues reading at the given byte offset, which should be the start of a document or
+    /// an offset returned by `offset`.
+    pub fn seek(&mut self, offset: usize) {
+        let offset = offset.min(self.input.len());
+        let before = &self.input[..offset];
+        self.pos = offset;
+        self.line = 1 + before.iter().filter(|&&ch| ch == b'\n').count();
+        self.col = match before.iter().rposition(|&ch| ch == b'\n') {
+            Some(nl) => offset - nl - 1,
+            None => offset,
+        };
+    }
+
+    fn advance_to(&mut self, offset: usize) {
+        let skipped = &self.input[self.pos..offset];
+        match skipped.iter().rposition(|&ch| ch == b'\n') {
+            Some(nl) => {
+                self.line += skipped.iter().filter(|&&ch| ch == b'\n').count();
+                self.col = skipped.len() - nl - 1;
+            }
+            None => self.col += skipped.len(),
+        }
+        self.pos = offset;
+    }
+
+    // returns the offsets where the next separator starts and ends
+    fn find_separator(&self) -> (usize, usize) {
+        let mut start = self.pos;
+        let mut content = false;
+        while start < self.input.len() {
+            let end = match self.input[start..].iter().position(|&ch| ch == b'\n') {
+                Some(nl) => start + nl + 1,
+                None => self.input.len(),
+            };
+            let line = str::from_utf8(&self.input[start..end]).map(str::trim);
+            let found = match self.separator {
+                Separator::None => false,
+                Separator::BlankLine => content && line == Ok(""),
+                Separator::Line(ref sep) => line == Ok(sep.as_str()),
+            };
+            if found {
+                return (start, end);
+            }
+            content |= line != Ok("");
+            start = end;
+        }
+        (self.input.len(), self.input.len())
+    }
+
+    fn reader_at(&self, bytes: &'a [u8]) -> Reader<SliceInput<'a>> {
+        let mut rdr = Reader::new(SliceInput::new(bytes));
+        rdr.set_position(self.line, self.col, self.pos);
+        rdr.set_comments(false);
+        rdr
+    }
+
+    fn next_unseparated(&mut self) -> Option<Result<Document<T>>> {
+        let mut de = Deserializer::from_reader(self.reader_at(&self.input[self.pos..]));
+        let res = match de.rdr.peek_spanned() {
+            Ok(None) => {
+                self.advance_to(self.input.len());
+                return None;
+            }
+            Ok(Some((_, span))) => de.read_value(PhantomData).map(|value| Document {
+                value,
+                start: span.start,
+                end: de.rdr.offset(),
+                line: span.line,
+            }),
+            Err(e) => Err(e),
+        };
+
+        let mut next = de.rdr.offset();
+        if res.is_err() {
+            // skip to the next line
+            next = match self.input[next..].iter().position(|&ch| ch == b'\n') {
+                Some(nl) => next + nl + 1,
+                None => self.input.len(),
+            };
+        }
+        self.advance_to(next.max(self.pos + 1).min(self.input.len()));
+        Some(res)
+    }
+
+    fn next_separated(&mut self) -> Option<Result<Document<T>>> {
+        while self.pos < self.input.len() {
+            let (sep_start, sep_end) = self.find_separator();
+            let text = &self.input[self.pos..sep_start];
+
+            // skip documents that only hold whitespace and comments
+            let empty = match self.reader_at(text).peek_spanned() {
+                Ok(None) => true,
+                Ok(Some(_)) | Err(_) => false,
+            };
+            let start = self.pos
+                + text
+                    .iter()
+                    .take_while(|ch| ch.is_ascii_whitespace())
+                    .count();
+            let trailing = text.iter().rev().take_while(|ch| ch.is_ascii_whitespace());
+            let end = (sep_start - trailing.count()).max(start);
+            self.advance_to(start);
+            let (line, col) = (self.line, self.col);
+            let res = if empty {
+                None
+            } else {
+                Some(
+                    from_document(&self.input[start..end], line, col, start).map(|value| {
+                        Document {
+                            value,
+                            start,
+                            end,
+                            line,
+                        }
+                    }),
+                )
+            };
+            self.advance_to(sep_end);
+            if res.is_some() {
+                return res;
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T> Iterator for DocumentStream<'a, T>
+where
+    T: de::DeserializeOwned,
+{
+    type Item = Result<Document<T>>;
+
+    fn next(&mut self) -> Option<Result<Document<T>>> {
+        if self.pos >= self.input.len() {
+            return None;
+        }
+        match self.separator {
+            Separator::None => self.next_unseparated(),
+            _ => self.next_separated(),
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+
+/// The result of `PushDeserializer::next_value`.
+#[derive(Debug, PartialEq)]
+pub enum Progress<T> {
+    /// The buffered input does not hold a complete value yet.
+    NeedMoreData,
+    /// A complete value was read.
+    Value(T),
+    /// `finish` was called and all input has been read.
+    Done,
+}
+
+/// Incremental deserializer for a stream of Hjson values that arrives in chunks.
+///
+/// Input is added with `feed` as it becomes available, e.g. from a non-blocking socket, and
+/// `next_value` returns each value once it is complete. Line and column numbers in errors refer
+/// to the whole stream. The input is scanned for the end of the next value as it arrives, and a
+/// value is deserialized when it is complete. A string or other value that is cut off at the end
+/// of the buffer is only scanned again from its start once a chunk brings a byte that could end
+/// it, like a quote or a line break, so a long value fed in small chunks costs linear time.
+///
+/// A value that does not match `T` is skipped after its error is returned, so the next call
+/// reads the value after it. A syntax error ends the stream, every later call returns it again.
+///
+/// ```rust
+/// use serde_hjson::de::{Progress, PushDeserializer};
+/// use serde_hjson::Value;
+///
+/// let mut de = PushDeserializer::<Value>::new();
+/// de.feed(b"{a: ");
+/// assert_eq!(de.next_value().unwrap(), Progress::NeedMoreData);
+/// de.feed(b"1}\n[");
+/// assert!(matches!(de.next_value().unwrap(), Progress::Value(_)));
+/// assert_eq!(de.next_value().unwrap(), Progress::NeedMoreData);
+/// ```
+pub struct PushDeserializer<T>
+where
+    T: de::DeserializeOwned,
+{
+    buf: Vec<u8>,
+    // where the buffer starts in the stream, always between two values
+    start: Checkpoint,
+    // how far the next value has been scanned
+    scan: Checkpoint,
+    // which bytes can end the value the scan stopped in, and how much of the
+    // buffer has been looked at for them
+    ends: Option<fn(u8) -> bool>,
+    seen: usize,
+    finished: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> PushDeserializer<T>
+where
+    T: de::DeserializeOwned,
+{
+    /// Creates an empty `PushDeserializer`.
+    pub fn new() -> PushDeserializer<T> {
+        PushDeserializer {
+            buf: Vec::new(),
+            start: Checkpoint::new(1, 0, 0),
+            scan: Checkpoint::new(1, 0, 0),
+            ends: None,
+            seen: 0,
+            finished: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Adds a chunk of input.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Marks the end of the input, so that a value at the end of the buffer counts as complete.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Returns the number of bytes that have been fed but not yet read as part of a value.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the number of bytes read as part of complete values so far.
+    pub fn offset(&self) -> usize {
+        self.start.offset()
+    }
+
+    /// Reads the next value from the buffered input.
+    pub fn next_value(&mut self) -> Result<Progress<T>> {
+        if let Some(ends) = self.ends {
+            if !self.finished && !self.buf[self.seen..].iter().any(|&b| ends(b)) {
+                self.seen = self.buf.len();
+                return Ok(Progress::NeedMoreData);
+            }
+            self.ends = None;
+        }
+
+        // continue the scan for the end of the value after the last token that
+        // was complete in the previous call
+        let scanned = self.scan.offset() - self.start.offset();
+        let mut rdr = Reader::resume(SliceInput::new(&self.buf[scanned..]), &self.scan);
+        loop {
+            let res = rdr.skip_token();
+            // a token that touches the end of the buffer might continue in the
+            // next chunk, e.g. a number or a quoteless string
+            if rdr.hit_eof() && !self.finished {
+                self.ends = rdr
+                    .value_start()
+                    .and_then(|offset| value_ends(&self.buf[offset - self.start.offset()..]));
+                self.seen = self.buf.len();
+                return Ok(Progress::NeedMoreData);
+            }
+            if !res? {
+                self.buf.clear();
+                return Ok(Progress::Done);
+            }
+            self.scan = rdr.checkpoint();
+            if rdr.at_top() {
+                break;
+            }
+        }
+
+        let mut rdr = Reader::new(SliceInput::new(&self.buf));
+        let (line, col) = self.start.position();
+        rdr.set_position(line, col, self.start.offset());
+        let res = Deserializer::from_reader(rdr).read_value(PhantomData);
+
+        // the value is consumed even if it could not be deserialized
+        self.buf.drain(..self.scan.offset() - self.start.offset());
+        self.start = self.scan.clone();
+        res.map(Progress::Value)
+    }
+}
+
+// Returns a test for the bytes that can end the value at the start of `value`,
+// which is cut off at the end of the buffer, or `None` if any byte can.
+fn value_ends(value: &[u8]) -> Option<fn(u8) -> bool> {
+    match value {
+        [b'"', ..] => Some(|b| b == b'"' || b == b'\\'),
+        [b'\'', b'\'', b'\'', ..] => Some(|b| b == b'\''),
+        // a quote or two might still become the start of a multiline string
+        [] | [b'\''] | [b'\'', b'\''] => None,
+        _ => Some(|b| matches!(b, b'\n' | b'\r' | b'\0' | b',' | b']' | b'}' | b'#' | b'/')),
+    }
+}
+
+impl<T> Default for PushDeserializer<T>
+where
+    T: de::DeserializeOwned,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+
 /// Decodes a Hjson value from an iterator over an iterator
 /// `Iterator<Item=u8>`.
 pub fn from_iter<I, T>(iter: I) -> Result<T>
@@ -762,17 +763,32 @@ where
     let fold: io::Result<Vec<_>> = iter.collect();
     let bytes = fold.map_err(Error::Io)?;
 
+    from_document(&bytes, 1, 0, 0)
+}
+
+// Decodes a complete document that starts at the given line, column and byte
+// offset of a larger input.
+fn from_document<T>(bytes: &[u8], line: usize, col: usize, offset: usize) -> Result<T>
+where
+    T: de::DeserializeOwned,
+{
     // deserialize tries first to decode with legacy support (new_for_root)
     // and then with the standard method if this fails.
     // todo: add compile switch
 
     // deserialize and make sure the whole stream has been consumed
-    let mut de = Deserializer::new_for_root(bytes.iter().copied());
-    de::Deserialize::deserialize(&mut de)
+    let mut rdr = Reader::new_for_root(SliceInput::new(bytes));
+    rdr.set_position(line, col, offset);
+    let mut de = Deserializer::from_reader(rdr);
+    de.read_value(PhantomData)
         .and_then(|x| de.end().map(|()| x))
-        .or_else(|_| {
-            let mut de2 = Deserializer::new(bytes.iter().copied());
-            de::Deserialize::deserialize(&mut de2).and_then(|x| de2.end().map(|()| x))
+        .or_else(|err| {
+            let mut rdr = Reader::new(SliceInput::new(bytes));
+            rdr.set_position(line, col, offset);
+            let mut de2 = Deserializer::from_reader(rdr);
+            de2.read_value(PhantomData)
+                .and_then(|x| de2.end().map(|()| x))
+                .map_err(|err2| root_error(bytes, err, err2))
         })
 
     /* without legacy support:
@@ -787,13 +803,188 @@ where
     */
 }
 
+// Picks the error to report when neither a root object without braces nor any
+// other value could be read. A data error of the root object is kept when the
+// input reads as such an object, like a missing field, or when it is in a member,
+// since then only the contents did not match the type.
+fn root_error(bytes: &[u8], root: Error, other: Error) -> Error {
+    match root {
+        Error::Data(_, ref path, ..) if !path.is_empty() || is_root_object(bytes) => root,
+        _ => other,
+    }
+}
+
+// Whether the input is a root object without braces.
+fn is_root_object(bytes: &[u8]) -> bool {
+    let mut rdr = Reader::new_for_root(SliceInput::new(bytes));
+    rdr.set_comments(false);
+    rdr.skip_value().and_then(|()| rdr.end()).is_ok()
+}
+
+// Moves the reader to the start of the value addressed by the pointer tokens,
+// skipping everything in between. Returns false if the path does not exist.
+fn seek_pointer<Iter>(rdr: &mut Reader<Iter>, tokens: &[String]) -> Result<bool>
+where
+    Iter: Input,
+{
+    for token in tokens {
+        match rdr.next_event()? {
+            Some(Event::StartObject) => loop {
+                match rdr.next_event()? {
+                    Some(Event::Key(key)) if key == token => break,
+                    Some(Event::Key(_)) => rdr.skip_value()?,
+                    _ => return Ok(false),
+                }
+            },
+            Some(Event::StartArray) => {
+                let Some(index) = parse_index(token) else {
+                    return Ok(false);
+                };
+                for _ in 0..index {
+                    if let Some(Event::EndArray) = rdr.peek_event()? {
+                        return Ok(false);
+                    }
+                    rdr.skip_value()?;
+                }
+                if let Some(Event::EndArray) = rdr.peek_event()? {
+                    return Ok(false);
+                }
+            }
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+fn from_slice_at_with<Iter, T>(rdr: Reader<Iter>, tokens: &[String]) -> Result<Option<T>>
+where
+    Iter: Input,
+    T: de::DeserializeOwned,
+{
+    let mut de = Deserializer::from_reader(rdr);
+    if !seek_pointer(&mut de.rdr, tokens)? {
+        return Ok(None);
+    }
+    de.read_value(PhantomData).map(Some).map_err(|err| {
+        tokens
+            .iter()
+            .rev()
+            .fold(err, |err, token| err.in_member(token))
+    })
+}
+
+/// Decodes only the value addressed by a JSON Pointer (RFC 6901) in a Hjson byte slice.
+///
+/// Everything before the addressed value is skipped without being decoded and anything after
+/// it is not read at all, so the rest of the document is not validated. Returns `Ok(None)` if
+/// the path does not exist.
+pub fn from_slice_at<T>(v: &[u8], pointer: &str) -> Result<Option<T>>
+where
+    T: de::DeserializeOwned,
+{
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return Ok(None);
+    }
+    let tokens: Vec<String> = pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect();
+
+    // like from_iter, try a root object without braces first
+    from_slice_at_with(Reader::new_for_root(SliceInput::new(v)), &tokens).or_else(|err| {
+        match from_slice_at_with(Reader::new(SliceInput::new(v)), &tokens) {
+            // the value was found in the root object but did not match the type
+            Ok(None) if matches!(err, Error::Data(..)) => Err(err),
+            result => result.map_err(|err2| root_error(v, err, err2)),
+        }
+    })
+}
+
+/// Decodes only the value addressed by a JSON Pointer (RFC 6901) in a Hjson `&str`.
+///
+/// ```rust
+/// let input = "services: {\n  db: {port: 5432}\n  web: {env: {mode: \"prod\"}}\n}";
+/// let mode: Option<String> = serde_hjson::from_str_at(input, "/services/web/env/mode").unwrap();
+/// assert_eq!(mode.as_deref(), Some("prod"));
+/// ```
+pub fn from_str_at<T>(s: &str, pointer: &str) -> Result<Option<T>>
+where
+    T: de::DeserializeOwned,
+{
+    from_slice_at(s.as_bytes(), pointer)
+}
+
+/// Decodes the Hjson value at the start of a `&str` and returns it together with the byte offset
+/// where the value ended.
+///
+/// Unlike `from_str`, the input may continue after the value. A root object must have braces
+/// and a quoteless string always runs to the end of its line, which is consumed with it.
+///
+/// ```rust
+/// let input = "{name: \"a\"}\nplain text after the value";
+/// let (value, end): (serde_hjson::Value, usize) = serde_hjson::from_str_prefix(input).unwrap();
+/// assert_eq!(value.find("name").and_then(|v| v.as_str()), Some("a"));
+/// assert_eq!(&input[end..], "\nplain text after the value");
+/// ```
+pub fn from_str_prefix<T>(s: &str) -> Result<(T, usize)>
+where
+    T: de::DeserializeOwned,
+{
+    let mut de = Deserializer::new(SliceInput::new(s.as_bytes()));
+    let value = de.read_value(PhantomData)?;
+    Ok((value, de.byte_offset()))
+}
+
+/// Splits Hjson front-matter off the start of a `&str` and decodes it.
+///
+/// The front-matter starts with a `---` line at the very beginning of the input and ends with
+/// the next `---` line. Returns the decoded value and the remaining text after the closing
+/// line, or `None` and the whole input if there is no front-matter. Error positions refer to
+/// the whole input.
+///
+/// ```rust
+/// let input = "---\ntitle: Hello\ndraft: true\n---\n# Hello\n";
+/// let (meta, body): (Option<serde_hjson::Value>, &str) =
+///     serde_hjson::from_front_matter(input).unwrap();
+/// assert_eq!(meta.unwrap().find("title").and_then(|v| v.as_str()), Some("Hello"));
+/// assert_eq!(body, "# Hello\n");
+/// ```
+pub fn from_front_matter<T>(s: &str) -> Result<(Option<T>, &str)>
+where
+    T: de::DeserializeOwned,
+{
+    fn is_delimiter(line: &str) -> bool {
+        line.trim_end() == "---"
+    }
+
+    let mut lines = s.split_inclusive('\n');
+    match lines.next() {
+        Some(first) if is_delimiter(first) && first.ends_with('\n') => {
+            let start = first.len();
+            let mut pos = start;
+            for line in lines {
+                if is_delimiter(line) {
+                    let value = from_document(&s.as_bytes()[start..pos], 2, 0, start)?;
+                    return Ok((Some(value), &s[pos + line.len()..]));
+                }
+                pos += line.len();
+            }
+            Ok((None, s))
+        }
+        _ => Ok((None, s)),
+    }
+}
+
 /// Decodes a Hjson value from a `std::io::Read`.
-pub fn from_reader<R, T>(rdr: R) -> Result<T>
+pub fn from_reader<R, T>(mut rdr: R) -> Result<T>
 where
     R: io::Read,
     T: de::DeserializeOwned,
 {
-    from_iter(rdr.bytes())
+    let mut bytes = Vec::new();
+    rdr.read_to_end(&mut bytes)?;
+    from_slice(&bytes)
 }
 
 /// Decodes a Hjson value from a byte slice `&[u8]`.
@@ -801,7 +992,7 @@ pub fn from_slice<T>(v: &[u8]) -> Result<T>
 where
     T: de::DeserializeOwned,
 {
-    from_iter(v.iter().map(|byte| Ok(*byte)))
+    from_document(v, 1, 0, 0)
 }
 
 /// Decodes a Hjson value from a `&str`.
@@ -809,10 +1000,5 @@ pub fn from_str<T>(s: &str) -> Result<T>
 where
     T: de::DeserializeOwned,
 {
-    if s.chars().last().map_or(false, char::is_whitespace) {
-        from_slice(s.as_bytes())
-    } else {
-        let s = format!("{s}\n");
-        from_slice(s.as_bytes())
-    }
+    from_slice(s.as_bytes())
 }
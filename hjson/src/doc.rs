@@ -0,0 +1,282 @@
+//! Doc comments for documented Hjson files.
+//!
+//! A type that implements `HjsonDoc` describes the comments of its fields and variants.
+//! `to_string_documented` writes them above the members they document, which turns a
+//! default value into a commented configuration file. With the `derive` feature,
+//! `#[derive(HjsonDoc)]` implements the trait from the `///` comments of a type, like this
+//! implementation for a `Config` with a documented `port` field:
+//!
+//! ```rust
+//! use serde::ser::{Serialize, SerializeStruct, Serializer};
+//! use serde_hjson::doc::{Doc, HjsonDoc, Member};
+//!
+//! /// Generated from the code.
+//! struct Config {
+//!     /// The port to listen on.
+//!     port: u16,
+//! }
+//!
+//! impl Serialize for Config {
+//!     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+//!         let mut s = serializer.serialize_struct("Config", 1)?;
+//!         s.serialize_field("port", &self.port)?;
+//!         s.end()
+//!     }
+//! }
+//!
+//! impl HjsonDoc for Config {
+//!     fn hjson_doc() -> Doc {
+//!         let mut doc = Doc::new(Some("Generated from the code."));
+//!         doc.members.push(Member {
+//!             name: "port",
+//!             comment: Some("The port to listen on."),
+//!             doc: || None,
+//!         });
+//!         doc
+//!     }
+//! }
+//!
+//! assert_eq!(
+//!     serde_hjson::to_string_documented(&Config { port: 8080 }).unwrap(),
+//!     "# Generated from the code.\n{\n  # The port to listen on.\n  port: 8080\n}"
+//! );
+//! ```
+//!
+//! The derive reads the `rename`, `rename_all`, `rename_all_fields`, `skip`,
+//! `skip_serializing`, `flatten` and `transparent` attributes of serde, so the comments are
+//! found under the serialized names. The members of field types are documented when the type
+//! implements `HjsonDoc`, which includes options, boxes, sequences and maps of such types.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::commented::Commented;
+
+/// A type with doc comments for its members.
+pub trait HjsonDoc {
+    /// Returns the documentation of the type.
+    fn hjson_doc() -> Doc;
+}
+
+/// Returns the documentation of a member, if its type has any.
+pub type DocFn = fn() -> Option<Doc>;
+
+/// The documentation of a type.
+#[derive(Clone, Debug, Default)]
+pub struct Doc {
+    /// The doc comment of the type, written at the top of a documented file.
+    pub comment: Option<&'static str>,
+    /// The fields of a struct, or the variants of an enum, by their serialized names.
+    pub members: Vec<Member>,
+    /// Whether `members` are enum variants. The variants are listed in the comment above a
+    /// member that holds the enum.
+    pub variants: bool,
+    /// The documentation of the elements of a sequence, or of the values of a map.
+    pub items: Option<DocFn>,
+}
+
+/// The documentation of a field or variant.
+#[derive(Clone, Debug)]
+pub struct Member {
+    /// The serialized name.
+    pub name: &'static str,
+    /// The doc comment.
+    pub comment: Option<&'static str>,
+    /// The documentation of the value.
+    pub doc: DocFn,
+}
+
+impl Doc {
+    /// Creates the documentation of a type with the given doc comment and no members.
+    pub fn new(comment: Option<&'static str>) -> Self {
+        Doc {
+            comment,
+            ..Doc::default()
+        }
+    }
+
+    /// Creates the documentation of a sequence or map whose elements have the documentation
+    /// of `T`.
+    pub fn items<T>() -> Self
+    where
+        T: ?Sized + HjsonDoc,
+    {
+        Doc {
+            items: Some(doc_of::<T>),
+            ..Doc::default()
+        }
+    }
+
+    // Finds the member with the given serialized name.
+    pub(crate) fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+impl Member {
+    // The comment above the member: its doc comment or else the one of its type,
+    // followed by the variants of its value.
+    pub(crate) fn full_comment(&self, doc: Option<&Doc>) -> Option<String> {
+        let mut lines = Vec::new();
+        if let Some(comment) = self.comment.or_else(|| doc.and_then(|doc| doc.comment)) {
+            lines.push(comment.to_owned());
+        }
+        if let Some(doc) = doc.filter(|doc| doc.variants) {
+            for variant in &doc.members {
+                lines.push(match variant.comment {
+                    Some(comment) => {
+                        format!("- {}: {}", variant.name, comment.replace('\n', "\n  "))
+                    }
+                    None => format!("- {}", variant.name),
+                });
+            }
+        }
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+}
+
+fn doc_of<T>() -> Option<Doc>
+where
+    T: ?Sized + HjsonDoc,
+{
+    Some(T::hjson_doc())
+}
+
+macro_rules! transparent_doc {
+    ($($ty:ident),*) => {
+        $(
+            impl<T> HjsonDoc for $ty<T>
+            where
+                T: ?Sized + HjsonDoc,
+            {
+                fn hjson_doc() -> Doc {
+                    T::hjson_doc()
+                }
+            }
+        )*
+    };
+}
+
+transparent_doc!(Box, Rc, Arc);
+
+impl<T> HjsonDoc for Option<T>
+where
+    T: HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        T::hjson_doc()
+    }
+}
+
+impl<T> HjsonDoc for Commented<T>
+where
+    T: HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        T::hjson_doc()
+    }
+}
+
+impl<T> HjsonDoc for &T
+where
+    T: ?Sized + HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        T::hjson_doc()
+    }
+}
+
+impl<T> HjsonDoc for [T]
+where
+    T: HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        Doc::items::<T>()
+    }
+}
+
+impl<T, const N: usize> HjsonDoc for [T; N]
+where
+    T: HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        Doc::items::<T>()
+    }
+}
+
+impl<T> HjsonDoc for Vec<T>
+where
+    T: HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        Doc::items::<T>()
+    }
+}
+
+impl<T> HjsonDoc for VecDeque<T>
+where
+    T: HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        Doc::items::<T>()
+    }
+}
+
+impl<K, V> HjsonDoc for BTreeMap<K, V>
+where
+    V: HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        Doc::items::<V>()
+    }
+}
+
+impl<K, V, S> HjsonDoc for HashMap<K, V, S>
+where
+    V: HjsonDoc,
+{
+    fn hjson_doc() -> Doc {
+        Doc::items::<V>()
+    }
+}
+
+// Used by the derive to find the documentation of field types that implement
+// `HjsonDoc`, without requiring it of every field type:
+// `(&&DocProbe::<T>(PhantomData)).doc_of()` picks `Documented` when `T: HjsonDoc`
+// and falls back to `Undocumented` otherwise.
+#[doc(hidden)]
+pub struct DocProbe<T: ?Sized>(pub PhantomData<T>);
+
+#[doc(hidden)]
+pub trait Documented {
+    fn doc_of(&self) -> Option<Doc>;
+}
+
+impl<T> Documented for &DocProbe<T>
+where
+    T: ?Sized + HjsonDoc,
+{
+    fn doc_of(&self) -> Option<Doc> {
+        doc_of::<T>()
+    }
+}
+
+#[doc(hidden)]
+pub trait Undocumented {
+    fn doc_of(&self) -> Option<Doc>;
+}
+
+impl<T> Undocumented for DocProbe<T>
+where
+    T: ?Sized,
+{
+    fn doc_of(&self) -> Option<Doc> {
+        None
+    }
+}
@@ -103,11 +103,21 @@ impl fmt::Debug for ErrorCode {
 
 /// This type represents all possible errors that can occur when serializing or deserializing a
 /// value into JSON.
+///
+/// More kinds of errors may be added, so matches on it need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// The JSON value had some syntatic error.
     Syntax(ErrorCode, usize, usize),
 
+    /// A value could not be deserialized into its type, like a string into a `u16` or an
+    /// object without a required field, or its `Serialize` implementation failed. The `String`
+    /// is the JSON Pointer of the value, like `/servers/2/port`, or empty for the root value or
+    /// when serializing. The line and column are where the value starts in the text, or 0 if
+    /// it was not read from text, like in `from_value`.
+    Data(ErrorCode, String, usize, usize),
+
     /// Some IO error occurred when serializing or deserializing a value.
     Io(io::Error),
 
@@ -120,6 +130,7 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::Syntax(..) => "syntax error",
+            Error::Data(..) => "data error",
             Error::Io(ref error) => error.description(),
             Error::FromUtf8(ref error) => error.description(),
         }
@@ -129,7 +140,7 @@ impl error::Error for Error {
         match *self {
             Error::Io(ref error) => Some(error),
             Error::FromUtf8(ref error) => Some(error),
-            Error::Syntax(..) => None,
+            Error::Syntax(..) | Error::Data(..) => None,
         }
     }
 }
@@ -140,12 +151,61 @@ impl fmt::Display for Error {
             Error::Syntax(ref code, line, col) => {
                 write!(fmt, "{:?} at line {} column {}", code, line, col)
             }
+            Error::Data(ref code, ref path, line, col) => {
+                if !path.is_empty() {
+                    write!(fmt, "{}: ", path)?;
+                }
+                write!(fmt, "{:?}", code)?;
+                if line > 0 {
+                    write!(fmt, " at line {} column {}", line, col)?;
+                }
+                Ok(())
+            }
             Error::Io(ref error) => fmt::Display::fmt(error, fmt),
             Error::FromUtf8(ref error) => fmt::Display::fmt(error, fmt),
         }
     }
 }
 
+impl Error {
+    /// Returns the JSON Pointer of the value that could not be deserialized, like
+    /// `/servers/2/port`, or `None` if this is not a data error.
+    ///
+    /// ```rust
+    /// use std::collections::BTreeMap;
+    ///
+    /// let err = serde_hjson::from_str::<BTreeMap<String, Vec<u16>>>("ports: [80, \"x\"]").unwrap_err();
+    /// assert_eq!(err.path(), Some("/ports/1"));
+    /// ```
+    pub fn path(&self) -> Option<&str> {
+        match *self {
+            Error::Data(_, ref path, ..) => Some(path),
+            _ => None,
+        }
+    }
+
+    // Prefixes the path of a data error with the key or index of the member that holds
+    // the value, as the error leaves the member.
+    pub(crate) fn in_member(self, token: &str) -> Error {
+        match self {
+            Error::Data(code, path, line, col) => {
+                let token = token.replace('~', "~0").replace('/', "~1");
+                Error::Data(code, format!("/{}{}", token, path), line, col)
+            }
+            error => error,
+        }
+    }
+
+    // Sets the position of a data error that has none yet, so it is where the
+    // innermost value that raised the error starts.
+    pub(crate) fn at(self, line: usize, col: usize) -> Error {
+        match self {
+            Error::Data(code, path, 0, _) => Error::Data(code, path, line, col),
+            error => error,
+        }
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Error {
         Error::Io(error)
@@ -160,14 +220,14 @@ impl From<FromUtf8Error> for Error {
 
 impl de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Error {
-        Error::Syntax(ErrorCode::Custom(msg.to_string()), 0, 0)
+        Error::Data(ErrorCode::Custom(msg.to_string()), String::new(), 0, 0)
     }
 }
 
 impl ser::Error for Error {
-    /// Raised when there is general error when deserializing a type.
+    /// Raised when there is general error when serializing a type.
     fn custom<T: fmt::Display>(msg: T) -> Error {
-        Error::Syntax(ErrorCode::Custom(msg.to_string()), 0, 0)
+        Error::Data(ErrorCode::Custom(msg.to_string()), String::new(), 0, 0)
     }
 }
 
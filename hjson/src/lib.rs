@@ -12,7 +12,7 @@
 //! * `F64`: equivalent to rust's `f64`
 //! * `String`: equivalent to rust's `String`
 //! * `Array`: equivalent to rust's `Vec<T>`, but also allowing objects of different types in the
-//!    same array
+//!   same array
 //! * `Object`: equivalent to rust's `serde_hjson::Map<String, serde_hjson::Value>`
 //! * `Null`
 //!
@@ -53,26 +53,53 @@
 #![allow(clippy::match_like_matches_macro)]
 #![allow(clippy::needless_doctest_main)]
 
-#[macro_use]
-extern crate lazy_static;
-
 extern crate core;
+#[cfg(feature = "async")]
+extern crate futures;
 #[cfg(feature = "preserve_order")]
 extern crate linked_hash_map;
+extern crate memchr;
 extern crate num_traits;
-extern crate regex;
+extern crate ryu;
 extern crate serde;
+#[cfg(feature = "derive")]
+extern crate serde_hjson_derive;
 
+pub use self::commented::Commented;
 pub use self::de::{
-    from_iter, from_reader, from_slice, from_str, Deserializer, StreamDeserializer,
+    from_front_matter, from_iter, from_reader, from_slice, from_slice_at, from_str, from_str_at,
+    from_str_prefix, Deserializer, DocumentStream, PushDeserializer, StreamDeserializer,
 };
+pub use self::doc::HjsonDoc;
 pub use self::error::{Error, ErrorCode, Result};
-pub use self::ser::{to_string, to_vec, to_writer, Serializer};
+pub use self::ser::{
+    to_json_string, to_json_string_pretty, to_string, to_string_documented, to_string_with_options,
+    to_vec, to_writer, to_writer_with_options, to_writer_with_source_map, BytesFormat,
+    FormatOptions, HjsonFormatter, JsonFormatter, MapWriter, MultilinePolicy, QuotePolicy,
+    SeqWriter, Serializer, SourceMap, SourceSpan,
+};
 pub use self::value::{from_value, to_value, Map, Value};
 
+/// Derives `HjsonDoc` from the `///` comments of a struct or enum.
+#[cfg(feature = "derive")]
+pub use serde_hjson_derive::HjsonDoc;
+
+#[cfg(feature = "color")]
+pub use self::color::{ColorFormatter, ColorTheme};
+
+#[cfg(feature = "async")]
+pub use self::async_io::{from_async_reader, to_async_writer, AsyncStreamDeserializer};
+
+#[cfg(feature = "async")]
+pub mod async_io;
 pub mod builder;
+#[cfg(feature = "color")]
+pub mod color;
+mod commented;
 pub mod de;
+pub mod doc;
 pub mod error;
+pub mod reader;
 pub mod ser;
 mod util;
 pub mod value;
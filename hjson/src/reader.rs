@@ -0,0 +1,906 @@
+//! Hjson Pull Parser
+//!
+//! This module provides a low level, event based Hjson parser with the type `Reader`. It reports
+//! the structure of a document as a sequence of `Event`s, each with the `Span` of input it was
+//! read from, without building a `Value` or requiring any `Deserialize` types.
+//!
+//! # Example
+//!
+//! ```rust
+//! use serde_hjson::reader::{Event, Reader};
+//!
+//! let mut rdr = Reader::new("{\n  name: web\n  port: 80\n}".bytes());
+//! let mut keys = Vec::new();
+//! while let Some(event) = rdr.next_event().unwrap() {
+//!     if let Event::Key(key) = event {
+//!         keys.push(key.to_owned());
+//!     }
+//! }
+//! assert_eq!(keys, ["name", "port"]);
+//! ```
+
+use std::borrow::Cow;
+use std::str;
+
+use super::error::{Error, ErrorCode, Result};
+use super::util::{ParseNumber, StringReader};
+
+pub use super::util::{Input, Number, SliceInput};
+
+/// An event reported by the `Reader`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// Start of an object. Also reported for a root object without braces.
+    StartObject,
+
+    /// End of an object.
+    EndObject,
+
+    /// Start of an array.
+    StartArray,
+
+    /// End of an array.
+    EndArray,
+
+    /// The name of an object member.
+    Key(&'a str),
+
+    /// A quoted, quoteless or multiline string.
+    String(&'a str),
+
+    /// A number.
+    Number(Number),
+
+    /// `true` or `false`.
+    Bool(bool),
+
+    /// `null`.
+    Null,
+
+    /// A comment, including its `#`, `//` or `/* */` delimiters. Bytes that are not valid
+    /// UTF-8 are replaced with `U+FFFD`.
+    Comment(&'a str),
+}
+
+/// The location of an `Event` in the input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the first byte.
+    pub start: usize,
+    /// Byte offset just past the last byte.
+    pub end: usize,
+    /// Line of the first byte, starting at 1.
+    pub line: usize,
+    /// Column of the first byte, starting at 1.
+    pub column: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key,
+    String,
+    Number(Number),
+    Bool(bool),
+    Null,
+    Comment,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Frame {
+    Object { root: bool },
+    Array,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    /// Between top-level values.
+    Top,
+    /// Before a root object without braces.
+    Root,
+    /// Expecting an array element or the end of the array.
+    Element { first: bool },
+    /// Expecting a member name or the end of the object.
+    Key { first: bool },
+    /// Expecting the `:` after a member name.
+    Colon,
+    /// Expecting a member value.
+    Value,
+}
+
+// The parser state between two tokens, to continue reading where an earlier reader
+// stopped on input that starts at the checkpoint.
+#[derive(Clone)]
+pub(crate) struct Checkpoint {
+    stack: Vec<Frame>,
+    state: State,
+    line: usize,
+    col: usize,
+    offset: usize,
+}
+
+impl Checkpoint {
+    pub(crate) fn new(line: usize, col: usize, offset: usize) -> Checkpoint {
+        Checkpoint {
+            stack: Vec::new(),
+            state: State::Top,
+            line,
+            col,
+            offset,
+        }
+    }
+
+    pub(crate) fn position(&self) -> (usize, usize) {
+        (self.line, self.col)
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// A pull parser that reads Hjson from an `Input` and reports `Event`s.
+///
+/// Once a top-level value is complete, further calls read the next value of the stream or
+/// return `None` at the end of the input.
+pub struct Reader<Iter: Input> {
+    rdr: StringReader<Iter>,
+    buf: Vec<u8>,
+    stack: Vec<Frame>,
+    state: State,
+    peeked: Option<(Token, Span)>,
+    comments: bool,
+    skipping: bool,
+    // where the last token started if it is a value
+    value_start: Option<usize>,
+}
+
+impl<Iter> Reader<Iter>
+where
+    Iter: Input,
+{
+    /// Creates the Hjson reader from an `Input`, like an `std::iter::Iterator` or a
+    /// `SliceInput`.
+    #[inline]
+    pub fn new(rdr: Iter) -> Reader<Iter> {
+        Reader {
+            rdr: StringReader::new(rdr),
+            buf: Vec::with_capacity(128),
+            stack: Vec::new(),
+            state: State::Top,
+            peeked: None,
+            comments: true,
+            skipping: false,
+            value_start: None,
+        }
+    }
+
+    /// Creates the Hjson reader for a root object without braces.
+    #[inline]
+    pub fn new_for_root(rdr: Iter) -> Reader<Iter> {
+        let mut res = Reader::new(rdr);
+        res.state = State::Root;
+        res
+    }
+
+    // Creates a reader that continues from `checkpoint` on input that starts there.
+    pub(crate) fn resume(rdr: Iter, checkpoint: &Checkpoint) -> Reader<Iter> {
+        let mut res = Reader::new(rdr);
+        res.rdr
+            .resume(checkpoint.line, checkpoint.col, checkpoint.offset);
+        res.stack = checkpoint.stack.clone();
+        res.state = checkpoint.state;
+        res.comments = false;
+        res
+    }
+
+    // The state after the last token read, which must not have been peeked.
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        debug_assert!(self.peeked.is_none());
+        let (line, col) = self.rdr.pos();
+        Checkpoint {
+            stack: self.stack.clone(),
+            state: self.state,
+            line,
+            col,
+            offset: self.rdr.offset(),
+        }
+    }
+
+    // Reads the next token without decoding it and returns whether there was one.
+    pub(crate) fn skip_token(&mut self) -> Result<bool> {
+        self.skipping = true;
+        let next = self.read_token();
+        self.skipping = false;
+        Ok(next?.is_some())
+    }
+
+    // where the last token read started if it is a value, i.e. where the value
+    // begins that is cut off if the reader hit the end of the input in it
+    pub(crate) fn value_start(&self) -> Option<usize> {
+        self.value_start
+    }
+
+    // whether the reader is between top-level values
+    pub(crate) fn at_top(&self) -> bool {
+        self.state == State::Top
+    }
+
+    /// Sets whether comments are reported as `Event::Comment` (the default) or skipped.
+    pub fn set_comments(&mut self, comments: bool) {
+        self.comments = comments;
+    }
+
+    /// Returns the next event, or `None` at the end of the input.
+    pub fn next_event(&mut self) -> Result<Option<Event<'_>>> {
+        Ok(self.next_spanned()?.map(|(event, _)| event))
+    }
+
+    /// Returns the next event together with its `Span`, or `None` at the end of the input.
+    pub fn next_spanned(&mut self) -> Result<Option<(Event<'_>, Span)>> {
+        let next = match self.peeked.take() {
+            Some(next) => Some(next),
+            None => self.read_token()?,
+        };
+        Ok(next.map(|(token, span)| (self.event(token), span)))
+    }
+
+    /// Returns the next event without consuming it.
+    pub fn peek_event(&mut self) -> Result<Option<Event<'_>>> {
+        Ok(self.peek_spanned()?.map(|(event, _)| event))
+    }
+
+    /// Returns the next event together with its `Span` without consuming it.
+    pub fn peek_spanned(&mut self) -> Result<Option<(Event<'_>, Span)>> {
+        if self.peeked.is_none() {
+            self.peeked = self.read_token()?;
+        }
+        Ok(self.peeked.map(|(token, span)| (self.event(token), span)))
+    }
+
+    /// Skips the next value, including everything nested in it, without reporting its events.
+    /// Strings in the skipped value are checked for invalid escapes but not copied.
+    pub fn skip_value(&mut self) -> Result<()> {
+        let mut depth = 0usize;
+        loop {
+            let token = match self.peeked.take() {
+                Some((token, _)) => Some(token),
+                None => {
+                    self.skipping = true;
+                    let next = self.read_token();
+                    self.skipping = false;
+                    next?.map(|(token, _)| token)
+                }
+            };
+            match token {
+                Some(Token::StartObject) | Some(Token::StartArray) => depth += 1,
+                Some(Token::EndObject) | Some(Token::EndArray) => {
+                    if depth == 0 {
+                        return Err(self.rdr.error(ErrorCode::ExpectedSomeValue));
+                    }
+                    depth -= 1;
+                }
+                Some(Token::Key) | Some(Token::Comment) => continue,
+                Some(_) => {}
+                None => return Err(self.rdr.error(ErrorCode::EOFWhileParsingValue)),
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns the number of nested objects and arrays the reader is currently in.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Returns the current line and column.
+    pub fn position(&self) -> (usize, usize) {
+        self.rdr.pos()
+    }
+
+    /// Returns the number of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.rdr.offset()
+    }
+
+    /// Sets the position of the start of the input, for input that continues an earlier part
+    /// of a document. The position is used for spans and error messages.
+    pub fn set_position(&mut self, line: usize, column: usize, offset: usize) {
+        self.rdr.resume(line, column, offset);
+    }
+
+    // whether the reader had to look at the end of the input, i.e. whether
+    // the events read so far could change if the input were longer
+    pub(crate) fn hit_eof(&self) -> bool {
+        self.rdr.hit_eof()
+    }
+
+    /// The `Reader::end` method should be called after the last event has been read. It
+    /// validates that the rest of the input only consists of whitespace and comments.
+    pub fn end(&mut self) -> Result<()> {
+        if self.peeked.is_none() {
+            self.skip_whitespace(false)?;
+            if self.rdr.eof()? {
+                return Ok(());
+            }
+        }
+        Err(self.rdr.error(ErrorCode::TrailingCharacters))
+    }
+
+    /// Creates an error at the current position.
+    pub fn error(&self, reason: ErrorCode) -> Error {
+        self.rdr.error(reason)
+    }
+
+    fn event(&self, token: Token) -> Event<'_> {
+        // the buffer only ever holds text that was validated in read_token
+        let text = || str::from_utf8(&self.buf).unwrap_or_default();
+        match token {
+            Token::StartObject => Event::StartObject,
+            Token::EndObject => Event::EndObject,
+            Token::StartArray => Event::StartArray,
+            Token::EndArray => Event::EndArray,
+            Token::Key => Event::Key(text()),
+            Token::String => Event::String(text()),
+            Token::Number(n) => Event::Number(n),
+            Token::Bool(b) => Event::Bool(b),
+            Token::Null => Event::Null,
+            Token::Comment => Event::Comment(text()),
+        }
+    }
+
+    fn span_from(&self, start: (usize, usize, usize)) -> Span {
+        Span {
+            start: start.0,
+            end: self.rdr.offset(),
+            line: start.1,
+            column: start.2 + 1,
+        }
+    }
+
+    fn mark(&self) -> (usize, usize, usize) {
+        let (line, col) = self.rdr.pos();
+        (self.rdr.offset(), line, col)
+    }
+
+    fn read_token(&mut self) -> Result<Option<(Token, Span)>> {
+        self.value_start = None;
+        if let Some(span) = self.skip_whitespace(self.comments)? {
+            return Ok(Some((Token::Comment, span)));
+        }
+
+        match self.state {
+            State::Top => {
+                if self.rdr.eof()? {
+                    return Ok(None);
+                }
+                self.parse_value().map(Some)
+            }
+            State::Root => {
+                if self.rdr.eof()? {
+                    return Ok(None);
+                }
+                let start = self.mark();
+                self.stack.push(Frame::Object { root: true });
+                self.state = State::Key { first: true };
+                Ok(Some((Token::StartObject, self.span_from(start))))
+            }
+            State::Element { first } => {
+                // in Hjson the comma is optional and trailing commas are allowed
+                if !first && self.rdr.peek()? == Some(b',') {
+                    self.rdr.eat_char();
+                    self.state = State::Element { first: true };
+                    return self.read_token();
+                }
+                match self.rdr.peek()? {
+                    Some(b']') => {
+                        let start = self.mark();
+                        self.rdr.eat_char();
+                        self.end_container();
+                        Ok(Some((Token::EndArray, self.span_from(start))))
+                    }
+                    Some(_) => self.parse_value().map(Some),
+                    None => Err(self.rdr.error(ErrorCode::EOFWhileParsingList)),
+                }
+            }
+            State::Key { first } => {
+                if !first && self.rdr.peek()? == Some(b',') {
+                    self.rdr.eat_char();
+                    self.state = State::Key { first: true };
+                    return self.read_token();
+                }
+                let root = self.stack.last() == Some(&Frame::Object { root: true });
+                let start = self.mark();
+                match self.rdr.peek()? {
+                    Some(b'}') => {
+                        if root {
+                            return Err(self.rdr.error(ErrorCode::TrailingCharacters));
+                        }
+                        self.rdr.eat_char();
+                        self.end_container();
+                        Ok(Some((Token::EndObject, self.span_from(start))))
+                    }
+                    Some(b'"') => {
+                        self.rdr.eat_char();
+                        self.parse_string()?;
+                        self.state = State::Colon;
+                        Ok(Some((Token::Key, self.span_from(start))))
+                    }
+                    Some(_) => {
+                        self.parse_keyname()?;
+                        self.state = State::Colon;
+                        let mut span = self.span_from(start);
+                        span.end = span.start + self.buf.len();
+                        Ok(Some((Token::Key, span)))
+                    }
+                    None => {
+                        if root {
+                            self.end_container();
+                            Ok(Some((Token::EndObject, self.span_from(start))))
+                        } else {
+                            Err(self.rdr.error(ErrorCode::EOFWhileParsingObject))
+                        }
+                    }
+                }
+            }
+            State::Colon => match self.rdr.next_char()? {
+                Some(b':') => {
+                    self.state = State::Value;
+                    self.read_token()
+                }
+                Some(_) => Err(self.rdr.error(ErrorCode::ExpectedColon)),
+                None => Err(self.rdr.error(ErrorCode::EOFWhileParsingObject)),
+            },
+            State::Value => self.parse_value().map(Some),
+        }
+    }
+
+    fn end_container(&mut self) {
+        self.stack.pop();
+        self.after_value();
+    }
+
+    fn after_value(&mut self) {
+        self.state = match self.stack.last() {
+            Some(Frame::Object { .. }) => State::Key { first: false },
+            Some(Frame::Array) => State::Element { first: false },
+            None => State::Top,
+        };
+    }
+
+    // Skips blanks and comments, until a comment is kept or the next byte does
+    // not start one.
+    fn skip_whitespace(&mut self, keep: bool) -> Result<Option<Span>> {
+        loop {
+            self.rdr.skip_blank();
+            let block = match self.rdr.peek_or_null()? {
+                b'#' => false,
+                b'/' => match self.rdr.peek_next(1)? {
+                    Some(b'/') => false,
+                    Some(b'*') => true,
+                    // a `/` that does not start a comment, even at the end of
+                    // the input, is part of a value
+                    _ => return Ok(None),
+                },
+                _ => return Ok(None),
+            };
+            let span = self.read_comment(block)?;
+            if keep {
+                if let Cow::Owned(text) = String::from_utf8_lossy(&self.buf) {
+                    self.buf = text.into_bytes();
+                }
+                return Ok(Some(span));
+            }
+        }
+    }
+
+    fn read_comment(&mut self, block: bool) -> Result<Span> {
+        let start = self.mark();
+        self.buf.clear();
+        if block {
+            self.buf.push(self.rdr.eat_char());
+            self.buf.push(self.rdr.eat_char());
+            while self.rdr.read_until(b"*", Some(&mut self.buf)).is_some()
+                && self.rdr.peek_next(1)? != Some(b'/')
+            {
+                self.buf.push(self.rdr.eat_char());
+            }
+            // an unterminated block comment ends with the input
+            for _ in 0..2 {
+                if let Some(ch) = self.rdr.next_char()? {
+                    self.buf.push(ch);
+                }
+            }
+        } else {
+            self.rdr.read_until(b"\n", Some(&mut self.buf));
+        }
+        Ok(self.span_from(start))
+    }
+
+    fn parse_value(&mut self) -> Result<(Token, Span)> {
+        if self.rdr.eof()? {
+            return Err(self.rdr.error(ErrorCode::EOFWhileParsingValue));
+        }
+
+        let start = self.mark();
+        self.value_start = Some(start.0);
+        let token = match self.rdr.peek_or_null()? {
+            b'"' => {
+                self.rdr.eat_char();
+                self.parse_string()?;
+                Token::String
+            }
+            b'[' => {
+                self.rdr.eat_char();
+                self.stack.push(Frame::Array);
+                self.state = State::Element { first: true };
+                return Ok((Token::StartArray, self.span_from(start)));
+            }
+            b'{' => {
+                self.rdr.eat_char();
+                self.stack.push(Frame::Object { root: false });
+                self.state = State::Key { first: true };
+                return Ok((Token::StartObject, self.span_from(start)));
+            }
+            b'\x00' => return Err(self.rdr.error(ErrorCode::ExpectedSomeValue)),
+            _ => {
+                let (token, range) = self.parse_tfnns()?;
+                self.after_value();
+                let mut span = self.span_from(start);
+                if let Some((lead, end)) = range {
+                    span.end = span.start + end;
+                    span.start += lead;
+                    span.column += lead;
+                }
+                return Ok((token, span));
+            }
+        };
+        self.after_value();
+        Ok((token, self.span_from(start)))
+    }
+
+    fn is_punctuator_char(ch: u8) -> bool {
+        match ch {
+            b'{' | b'}' | b'[' | b']' | b',' | b':' => true,
+            _ => false,
+        }
+    }
+
+    fn parse_keyname(&mut self) -> Result<()> {
+        // quotes for keys are optional in Hjson
+        // unless they include {}[],: or whitespace.
+        // assume whitespace was already eaten
+
+        self.buf.clear();
+
+        let mut space: Option<usize> = None;
+        loop {
+            let ch = self.rdr.peek_or_null()?;
+
+            if ch == b':' {
+                if self.buf.is_empty() {
+                    return Err(self.rdr.error(ErrorCode::Custom(
+                        "Found ':' but no key name (for an empty key name use quotes)".to_string(),
+                    )));
+                } else if space.is_some() && space.unwrap() != self.buf.len() {
+                    return Err(self.rdr.error(ErrorCode::Custom(
+                        "Found whitespace in your key name (use quotes to include)".to_string(),
+                    )));
+                }
+                return match str::from_utf8(&self.buf) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(self.rdr.error(ErrorCode::InvalidUnicodeCodePoint)),
+                };
+            }
+
+            self.rdr.eat_char();
+            if ch <= b' ' {
+                if ch == 0 {
+                    return Err(self.rdr.error(ErrorCode::EOFWhileParsingObject));
+                } else if space.is_none() {
+                    space = Some(self.buf.len());
+                }
+            } else if Self::is_punctuator_char(ch) {
+                return Err(self.rdr.error(ErrorCode::Custom("Found a punctuator where a key name was expected (check your syntax or use quotes if the key name includes {}[],: or whitespace)".to_string())));
+            } else {
+                self.buf.push(ch);
+            }
+        }
+    }
+
+    // returns the token and, for quoteless strings, the range of the
+    // trimmed value relative to its start
+    fn parse_tfnns(&mut self) -> Result<(Token, Option<(usize, usize)>)> {
+        // Hjson strings can be quoteless
+        // returns string, true, false, or null.
+        self.buf.clear();
+
+        let first = self.rdr.peek()?.unwrap();
+
+        if Self::is_punctuator_char(first) {
+            return Err(self.rdr.error(ErrorCode::PunctuatorInQlString));
+        }
+
+        loop {
+            let ch = self.rdr.peek_or_null()?;
+
+            let is_eol = ch == b'\r' || ch == b'\n' || ch == b'\x00';
+            let is_comment = ch == b'#'
+                || ch == b'/' && {
+                    let next = self.rdr.peek_next(1)?.unwrap_or(b'\x00');
+                    next == b'/' || next == b'*'
+                };
+            if is_eol || is_comment || ch == b',' || ch == b'}' || ch == b']' {
+                let trimmed = str::from_utf8(&self.buf).map(str::trim);
+                let len = trimmed.map_or(0, str::len);
+                match self.buf[0] {
+                    b'f' if trimmed == Ok("false") => {
+                        return Ok((Token::Bool(false), Some((0, len))));
+                    }
+                    b'n' if trimmed == Ok("null") => {
+                        return Ok((Token::Null, Some((0, len))));
+                    }
+                    b't' if trimmed == Ok("true") => {
+                        return Ok((Token::Bool(true), Some((0, len))));
+                    }
+                    chf if chf == b'-' || chf.is_ascii_digit() => {
+                        let mut pn = ParseNumber::new(SliceInput::new(&self.buf));
+                        if let Ok(n) = pn.parse(false) {
+                            return Ok((Token::Number(n), Some((0, len))));
+                        }
+                        // not a number, continue
+                    }
+                    _ => {}
+                }
+                if is_eol {
+                    self.rdr.eat_char();
+                    if self.skipping {
+                        return Ok((Token::String, None));
+                    }
+                    // remove any whitespace at the end (ignored in quoteless strings)
+                    let Ok(s) = str::from_utf8(&self.buf) else {
+                        return Err(self.rdr.error(ErrorCode::EOFWhileParsingString));
+                    };
+                    let lead = s.len() - s.trim_start().len();
+                    let end = s.trim_end().len();
+                    self.buf.truncate(end);
+                    self.buf.drain(..lead);
+                    return Ok((Token::String, Some((lead, end))));
+                }
+            }
+            self.rdr.eat_char();
+            // when skipping, only keep what is needed to tell strings from
+            // keywords, numbers and multiline strings
+            if !self.skipping || self.buf.len() < 3 || b"ftn-0123456789".contains(&self.buf[0]) {
+                self.buf.push(ch);
+            }
+
+            if self.buf == b"'''" {
+                self.parse_ml_string()?;
+                return Ok((Token::String, None));
+            }
+        }
+    }
+
+    fn decode_hex_escape(&mut self) -> Result<u16> {
+        let mut i = 0;
+        let mut n = 0u16;
+        while i < 4 && !self.rdr.eof()? {
+            n = match self.rdr.next_char_or_null()? {
+                c @ b'0'..=b'9' => n * 16_u16 + ((c as u16) - (b'0' as u16)),
+                b'a' | b'A' => n * 16_u16 + 10_u16,
+                b'b' | b'B' => n * 16_u16 + 11_u16,
+                b'c' | b'C' => n * 16_u16 + 12_u16,
+                b'd' | b'D' => n * 16_u16 + 13_u16,
+                b'e' | b'E' => n * 16_u16 + 14_u16,
+                b'f' | b'F' => n * 16_u16 + 15_u16,
+                _ => {
+                    return Err(self.rdr.error(ErrorCode::InvalidEscape));
+                }
+            };
+
+            i += 1;
+        }
+
+        // Error out if we didn't parse 4 digits.
+        if i != 4 {
+            return Err(self.rdr.error(ErrorCode::InvalidEscape));
+        }
+
+        Ok(n)
+    }
+
+    fn ml_skip_white(&mut self) -> Result<bool> {
+        match self.rdr.peek_or_null()? {
+            b' ' | b'\t' | b'\r' => {
+                self.rdr.eat_char();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn ml_skip_indent(&mut self, indent: usize) -> Result<()> {
+        let mut skip = indent;
+        while self.ml_skip_white()? && skip > 0 {
+            skip -= 1;
+        }
+        Ok(())
+    }
+
+    fn parse_ml_string(&mut self) -> Result<()> {
+        self.buf.clear();
+
+        // Parse a multiline string value.
+        let mut triple = 0;
+
+        // we are at ''' +1 - get indent
+        let (_, col) = self.rdr.pos();
+
+        // Fallback machanism if col is improper
+        let indent = col.saturating_sub(4);
+
+        // skip white/to (newline)
+        while self.ml_skip_white()? {}
+        if self.rdr.peek_or_null()? == b'\n' {
+            self.rdr.eat_char();
+            self.ml_skip_indent(indent)?;
+        }
+
+        // When parsing multiline string values, we must look for ' characters.
+        loop {
+            if triple == 0 {
+                let out = if self.skipping {
+                    None
+                } else {
+                    Some(&mut self.buf)
+                };
+                self.rdr.read_until(b"\'\r\n", out);
+            }
+            if self.rdr.eof()? {
+                return Err(self.rdr.error(ErrorCode::EOFWhileParsingString));
+            } // todo error("Bad multiline string");
+            let ch = self.rdr.next_char_or_null()?;
+
+            if ch == b'\'' {
+                triple += 1;
+                if triple == 3 {
+                    if self.buf.last() == Some(&b'\n') {
+                        self.buf.pop();
+                    }
+                    if self.skipping {
+                        return Ok(());
+                    }
+                    return match str::from_utf8(&self.buf) {
+                        Ok(_) => Ok(()),
+                        Err(_) => Err(self.rdr.error(ErrorCode::InvalidUnicodeCodePoint)),
+                    };
+                } else {
+                    continue;
+                }
+            }
+
+            if self.skipping {
+                triple = 0;
+            }
+            while triple > 0 {
+                self.buf.push(b'\'');
+                triple -= 1;
+            }
+
+            if ch != b'\r' && !self.skipping {
+                self.buf.push(ch);
+            }
+            if ch == b'\n' {
+                self.ml_skip_indent(indent)?;
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<()> {
+        self.buf.clear();
+
+        loop {
+            let out = if self.skipping {
+                None
+            } else {
+                Some(&mut self.buf)
+            };
+            self.rdr.read_until(b"\"\\", out);
+            let Some(ch) = self.rdr.next_char()? else {
+                return Err(self.rdr.error(ErrorCode::EOFWhileParsingString));
+            };
+
+            match ch {
+                b'"' if self.skipping => return Ok(()),
+                b'"' => {
+                    return match str::from_utf8(&self.buf) {
+                        Ok(_) => Ok(()),
+                        Err(_) => Err(self.rdr.error(ErrorCode::InvalidUnicodeCodePoint)),
+                    };
+                }
+                b'\\' => {
+                    let Some(ch) = self.rdr.next_char()? else {
+                        return Err(self.rdr.error(ErrorCode::EOFWhileParsingString));
+                    };
+
+                    match ch {
+                        b'"' => self.buf.push(b'"'),
+                        b'\\' => self.buf.push(b'\\'),
+                        b'/' => self.buf.push(b'/'),
+                        b'b' => self.buf.push(b'\x08'),
+                        b'f' => self.buf.push(b'\x0c'),
+                        b'n' => self.buf.push(b'\n'),
+                        b'r' => self.buf.push(b'\r'),
+                        b't' => self.buf.push(b'\t'),
+                        b'u' => {
+                            let c = match self.decode_hex_escape()? {
+                                0xDC00..=0xDFFF => {
+                                    return Err(self
+                                        .rdr
+                                        .error(ErrorCode::LoneLeadingSurrogateInHexEscape));
+                                }
+
+                                // Non-BMP characters are encoded as a sequence of
+                                // two hex escapes, representing UTF-16 surrogates.
+                                n1 @ 0xD800..=0xDBFF => {
+                                    match (self.rdr.next_char()?, self.rdr.next_char()?) {
+                                        (Some(b'\\'), Some(b'u')) => (),
+                                        _ => {
+                                            return Err(self
+                                                .rdr
+                                                .error(ErrorCode::UnexpectedEndOfHexEscape));
+                                        }
+                                    }
+
+                                    let n2 = self.decode_hex_escape()?;
+
+                                    if !(0xDC00..=0xDFFF).contains(&n2) {
+                                        return Err(self
+                                            .rdr
+                                            .error(ErrorCode::LoneLeadingSurrogateInHexEscape));
+                                    }
+
+                                    let n = (((n1 - 0xD800) as u32) << 10 | (n2 - 0xDC00) as u32)
+                                        + 0x1_0000;
+
+                                    match char::from_u32(n) {
+                                        Some(c) => c,
+                                        None => {
+                                            return Err(self
+                                                .rdr
+                                                .error(ErrorCode::InvalidUnicodeCodePoint));
+                                        }
+                                    }
+                                }
+
+                                n => match char::from_u32(n as u32) {
+                                    Some(c) => c,
+                                    None => {
+                                        return Err(self
+                                            .rdr
+                                            .error(ErrorCode::InvalidUnicodeCodePoint));
+                                    }
+                                },
+                            };
+
+                            self.buf.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        _ => {
+                            return Err(self.rdr.error(ErrorCode::InvalidEscape));
+                        }
+                    }
+                }
+                _ if self.skipping => {}
+                ch => {
+                    self.buf.push(ch);
+                }
+            }
+        }
+    }
+}
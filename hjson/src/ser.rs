@@ -2,24 +2,320 @@
 //!
 //! This module provides for Hjson serialization with the type `Serializer`.
 
-use std::fmt::{Display, LowerExp};
-use std::io;
-use std::num::FpCategory;
-
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::str;
+use std::sync::Arc;
+
+use super::commented::{self, Commented, Comments};
+use super::doc::{Doc, HjsonDoc};
 use super::error::{Error, ErrorCode, Result};
 use serde::ser;
 
-use super::util::ParseNumber;
-
-use regex::Regex;
+use super::util::{encode_base64, encode_hex, ParseNumber};
 
 /// A structure for serializing Rust values into Hjson.
 pub struct Serializer<W, F> {
-    writer: W,
+    writer: Output<W>,
     formatter: F,
+    // the JSON Pointer of the value being written
+    path: String,
+    policy: Arc<KeyPolicy>,
+    // set once the comment above an object member is written before its key
+    comment_written: bool,
+    // the length of the path of a string that a trailing comment follows
+    quote_at: Option<usize>,
+    // the documentation of the values being written, empty without doc comments
+    docs: Vec<Option<Doc>>,
+    // how many of the values being written are on a single line
+    inline: usize,
+    // set while the members of the root object are written without braces
+    braceless_root: bool,
+}
+
+// Writes to the inner writer, or to the innermost buffer while the members of
+// objects with sorted keys are collected.
+struct Output<W> {
+    inner: W,
+    buffers: Vec<Vec<u8>>,
+    // the number of bytes written to the inner writer
+    offset: usize,
+    map: Option<Box<SpanRecorder>>,
+}
+
+// The output of a buffer, with the spans that were recorded in it relative to
+// its start.
+struct Buffered {
+    bytes: Vec<u8>,
+    spans: Vec<(String, usize, usize)>,
+}
+
+// Records the byte ranges of the values for a `SourceMap`.
+#[derive(Default)]
+struct SpanRecorder {
+    // the offsets where lines of the inner writer start, after the first line
+    line_starts: Vec<usize>,
+    // the finished spans of the inner writer, followed by those of each buffer
+    levels: Vec<Vec<(String, usize, usize)>>,
+    // the spans being written with their level, which start at the next byte
+    // that is not whitespace
+    open: Vec<(String, usize, Option<usize>)>,
+}
+
+impl<W> Output<W>
+where
+    W: io::Write,
+{
+    // The position in the innermost buffer, or in the output without buffers.
+    fn position(&self) -> usize {
+        match self.buffers.last() {
+            Some(buffer) => buffer.len(),
+            None => self.offset,
+        }
+    }
+
+    fn push_buffer(&mut self) {
+        self.buffers.push(Vec::new());
+        if let Some(ref mut map) = self.map {
+            map.levels.push(Vec::new());
+        }
+    }
+
+    fn pop_buffer(&mut self) -> Buffered {
+        let spans = match self.map {
+            Some(ref mut map) if map.levels.len() > 1 => map.levels.pop().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        Buffered {
+            bytes: self.buffers.pop().unwrap_or_default(),
+            spans,
+        }
+    }
+
+    // Writes a buffer that was collected earlier, moving its spans to where it
+    // ends up.
+    fn write_buffered(&mut self, buffered: &Buffered) -> io::Result<()> {
+        let start = self.position();
+        io::Write::write_all(self, &buffered.bytes)?;
+        if let Some(ref mut map) = self.map {
+            if let Some(level) = map.levels.last_mut() {
+                level.extend(
+                    buffered
+                        .spans
+                        .iter()
+                        .map(|(path, from, to)| (path.clone(), start + from, start + to)),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn open_span(&mut self, path: &str) {
+        let level = self.buffers.len();
+        if let Some(ref mut map) = self.map {
+            map.open.push((path.to_owned(), level, None));
+        }
+    }
+
+    fn close_span(&mut self) {
+        let end = self.position();
+        if let Some(ref mut map) = self.map {
+            if let Some((path, level, start)) = map.open.pop() {
+                if let Some(spans) = map.levels.get_mut(level) {
+                    spans.push((path, start.unwrap_or(end), end));
+                }
+            }
+        }
+    }
+
+    // Starts the open spans at the first byte that is not whitespace, and
+    // remembers where lines start.
+    fn record(&mut self, position: usize, buf: &[u8]) {
+        let level = self.buffers.len();
+        let Some(ref mut map) = self.map else {
+            return;
+        };
+        if let Some(first) = buf
+            .iter()
+            .position(|&ch| !matches!(ch, b' ' | b'\t' | b'\r' | b'\n'))
+        {
+            for (_, span_level, start) in map.open.iter_mut().rev() {
+                if start.is_some() || *span_level != level {
+                    break;
+                }
+                *start = Some(position + first);
+            }
+        }
+        if level == 0 {
+            map.line_starts
+                .extend(memchr::memchr_iter(b'\n', buf).map(|i| position + i + 1));
+        }
+    }
+}
+
+impl<W> io::Write for Output<W>
+where
+    W: io::Write,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let position = self.position();
+        let written = match self.buffers.last_mut() {
+            Some(buffer) => {
+                buffer.extend_from_slice(buf);
+                buf.len()
+            }
+            None => self.inner.write(buf)?,
+        };
+        self.record(position, &buf[..written]);
+        if self.buffers.is_empty() {
+            self.offset += written;
+        }
+        Ok(written)
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let position = self.position();
+        match self.buffers.last_mut() {
+            Some(buffer) => buffer.extend_from_slice(buf),
+            None => self.inner.write_all(buf)?,
+        }
+        self.record(position, buf);
+        if self.buffers.is_empty() {
+            self.offset += buf.len();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Where the serializer wrote each value, by the JSON Pointer of the value. See
+/// `Serializer::record_source_map`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    spans: BTreeMap<String, SourceSpan>,
+}
+
+/// The part of the output that a value was written to. For object members it starts at the
+/// key, comments above the key and after the value are not included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SourceSpan {
+    /// Byte offset of the first byte.
+    pub start: usize,
+    /// Byte offset just past the last byte.
+    pub end: usize,
+    /// Line of the first byte, starting at 1.
+    pub line: usize,
+    /// Column of the first byte, starting at 1.
+    pub column: usize,
+    /// Line of the last byte, starting at 1.
+    pub end_line: usize,
+    /// Column just past the last byte, starting at 1.
+    pub end_column: usize,
+}
+
+impl SourceMap {
+    /// Returns where the value at the JSON Pointer `pointer` was written.
+    pub fn get(&self, pointer: &str) -> Option<&SourceSpan> {
+        self.spans.get(pointer)
+    }
+
+    /// Iterates over the spans, ordered by their JSON Pointers.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SourceSpan)> {
+        self.spans
+            .iter()
+            .map(|(pointer, span)| (pointer.as_str(), span))
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Returns whether no values were written.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
+
+impl SpanRecorder {
+    // Converts a byte offset to its line and column.
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = if line == 0 {
+            0
+        } else {
+            self.line_starts[line - 1]
+        };
+        (line + 1, offset - line_start + 1)
+    }
+
+    fn source_map(&self, offset: usize) -> SourceMap {
+        // the root is still open
+        let open = self
+            .open
+            .iter()
+            .filter(|(_, level, _)| *level == 0)
+            .map(|(path, _, start)| (path, start.unwrap_or(offset), offset));
+        let finished = self
+            .levels
+            .first()
+            .into_iter()
+            .flatten()
+            .map(|(path, start, end)| (path, *start, *end));
+        let spans = finished
+            .chain(open)
+            .map(|(path, start, end)| {
+                let (line, column) = self.line_column(start);
+                let (end_line, end_column) = self.line_column(end);
+                let span = SourceSpan {
+                    start,
+                    end,
+                    line,
+                    column,
+                    end_line,
+                    end_column,
+                };
+                (path.clone(), span)
+            })
+            .collect();
+        SourceMap { spans }
+    }
+}
+
+// Compares the keys of object members.
+type KeyOrder = Arc<dyn Fn(&str, &str) -> Ordering + Send + Sync>;
+
+// Which object members are written, and in which order.
+#[derive(Clone, Default)]
+struct KeyPolicy {
+    sort: Option<KeyOrder>,
+    skip: Vec<String>,
+    redact: Vec<String>,
+    placeholder: String,
+    skip_nulls: bool,
+}
+
+impl KeyPolicy {
+    fn matches(patterns: &[String], path: &str, key: &str) -> bool {
+        patterns.iter().any(|pattern| {
+            if pattern.starts_with('/') {
+                glob_match(pattern, path)
+            } else {
+                glob_match(pattern, key)
+            }
+        })
+    }
 }
 
-impl<'a, W> Serializer<W, HjsonFormatter<'a>>
+impl<W> Serializer<W, HjsonFormatter>
 where
     W: io::Write,
 {
@@ -39,13 +335,411 @@ where
     /// specified.
     #[inline]
     pub fn with_formatter(writer: W, formatter: F) -> Self {
-        Self { writer, formatter }
+        Self {
+            writer: Output {
+                inner: writer,
+                buffers: Vec::new(),
+                offset: 0,
+                map: None,
+            },
+            formatter,
+            path: String::new(),
+            policy: Arc::new(KeyPolicy::default()),
+            comment_written: false,
+            quote_at: None,
+            docs: Vec::new(),
+            inline: 0,
+            braceless_root: false,
+        }
+    }
+
+    /// Records where each value is written, which `source_map` returns.
+    pub fn record_source_map(mut self, record: bool) -> Self {
+        self.writer.map = if record {
+            let mut map = SpanRecorder {
+                levels: vec![Vec::new()],
+                ..SpanRecorder::default()
+            };
+            map.open.push((String::new(), 0, None));
+            Some(Box::new(map))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Returns where each value has been written, if `record_source_map` is enabled.
+    pub fn source_map(&self) -> Option<SourceMap> {
+        self.writer
+            .map
+            .as_ref()
+            .map(|map| map.source_map(self.writer.offset))
+    }
+
+    /// Writes the doc comments in `doc` above the members they document. The comment of the
+    /// type itself is only written by `to_string_documented`.
+    pub fn doc_comments(mut self, doc: Doc) -> Self {
+        self.docs = vec![Some(doc)];
+        self
+    }
+
+    /// Writes the members of objects sorted by their keys, instead of in the order they are
+    /// serialized.
+    pub fn sort_keys(mut self, sort: bool) -> Self {
+        Arc::make_mut(&mut self.policy).sort = if sort {
+            Some(Arc::new(|a: &str, b: &str| a.cmp(b)))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Writes the members of objects sorted with the given comparison of their keys.
+    pub fn sort_keys_by<C>(mut self, compare: C) -> Self
+    where
+        C: Fn(&str, &str) -> Ordering + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.policy).sort = Some(Arc::new(compare));
+        self
+    }
+
+    /// Leaves out the object members that match one of the patterns.
+    ///
+    /// A pattern is matched against the key of a member, or against its JSON Pointer if it
+    /// starts with a `/`. A `*` in a pattern stands for any characters except `/`, so
+    /// `/servers/*/password` matches the `password` of every server.
+    pub fn skip_keys(mut self, patterns: &[&str]) -> Self {
+        let policy = Arc::make_mut(&mut self.policy);
+        policy.skip.extend(patterns.iter().map(|p| p.to_string()));
+        self
+    }
+
+    /// Writes the `placeholder` string instead of the values of the object members that match
+    /// one of the patterns. The patterns are matched like in `skip_keys`.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_hjson::Serializer;
+    ///
+    /// let value: serde_hjson::Value =
+    ///     serde_hjson::from_str("{user: \"admin\", password: \"hunter2\", cache: null}").unwrap();
+    /// let mut out = Vec::new();
+    /// let mut ser = Serializer::new(&mut out)
+    ///     .sort_keys(true)
+    ///     .redact_keys(&["password"], "***")
+    ///     .skip_nulls(true);
+    /// value.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "{\n  password: ***\n  user: admin\n}"
+    /// );
+    /// ```
+    pub fn redact_keys(mut self, patterns: &[&str], placeholder: &str) -> Self {
+        let policy = Arc::make_mut(&mut self.policy);
+        policy.redact.extend(patterns.iter().map(|p| p.to_string()));
+        policy.placeholder = placeholder.to_owned();
+        self
+    }
+
+    /// Leaves out the object members whose values are written as `null`, like `None`.
+    pub fn skip_nulls(mut self, skip: bool) -> Self {
+        Arc::make_mut(&mut self.policy).skip_nulls = skip;
+        self
     }
 
     /// Unwrap the `Writer` from the `Serializer`.
     #[inline]
     pub fn into_inner(self) -> W {
-        self.writer
+        self.writer.inner
+    }
+
+    /// Starts an array whose elements are serialized straight to the writer as they are
+    /// produced, without collecting them first. The array is closed by `SeqWriter::end`.
+    ///
+    /// ```rust
+    /// let mut writer = Vec::new();
+    /// let mut ser = serde_hjson::Serializer::new(&mut writer);
+    /// let mut seq = ser.seq_writer().unwrap();
+    /// for i in 0..3 {
+    ///     seq.write_element(&i).unwrap();
+    /// }
+    /// seq.end().unwrap();
+    /// assert_eq!(writer, b"[\n  0\n  1\n  2\n]");
+    /// ```
+    pub fn seq_writer(&mut self) -> Result<SeqWriter<'_, W, F>> {
+        let compound = ser::Serializer::serialize_seq(self, None)?;
+        Ok(SeqWriter { compound })
+    }
+
+    /// Starts an object whose members are serialized straight to the writer as they are
+    /// produced. The object is closed by `MapWriter::end`. With `sort_keys` the members are
+    /// kept until the object is closed.
+    pub fn map_writer(&mut self) -> Result<MapWriter<'_, W, F>> {
+        let compound = ser::Serializer::serialize_map(self, None)?;
+        Ok(MapWriter { compound })
+    }
+
+    // Appends a key or an array index to the path, escaped as a JSON Pointer token.
+    fn push_path(&mut self, token: &str) {
+        self.path.push('/');
+        for ch in token.chars() {
+            match ch {
+                '~' => self.path.push_str("~0"),
+                '/' => self.path.push_str("~1"),
+                _ => self.path.push(ch),
+            }
+        }
+    }
+
+    fn serialize_integer<I>(&mut self, value: I) -> Result<()>
+    where
+        I: Display,
+    {
+        // i64::MIN and u64::MAX have 20 characters
+        let mut buf = [0u8; 20];
+        let mut cursor = io::Cursor::new(&mut buf[..]);
+        write!(cursor, "{}", value)?;
+        let len = cursor.position() as usize;
+        let text = str::from_utf8(&buf[..len]).unwrap();
+        self.formatter
+            .write_number(&mut self.writer, &self.path, text)
+    }
+
+    fn serialize_float<N>(&mut self, value: N, finite: bool) -> Result<()>
+    where
+        N: ryu::Float,
+    {
+        if finite {
+            let mut text = fmt_small(value);
+            if self.formatter.options().float_point && !text.contains(['.', 'e']) {
+                text.push_str(".0");
+            }
+            self.formatter
+                .write_number(&mut self.writer, &self.path, &text)
+        } else {
+            self.formatter.write_null(&mut self.writer, &self.path)
+        }
+    }
+
+    // Writes an object member whose key is already on the path.
+    fn serialize_member<T>(
+        &mut self,
+        key: &str,
+        value: &T,
+        redact: bool,
+        comment: Option<&str>,
+    ) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        if let Some(comment) = comment {
+            self.write_comment(comment, false)?;
+            self.comment_written = true;
+        }
+        self.writer.open_span(&self.path);
+        self.formatter
+            .write_key(&mut self.writer, &self.path, key)?;
+        self.formatter.colon(&mut self.writer)?;
+        self.formatter
+            .begin_object_value(&mut self.writer, &self.path)?;
+        if redact {
+            self.formatter
+                .write_string(&mut self.writer, &self.path, &self.policy.placeholder)?;
+        } else {
+            self.serialize_value(value, Some(key))?;
+        }
+        self.comment_written = false;
+        self.writer.close_span();
+        self.formatter
+            .end_object_value(&mut self.writer, &self.path)
+    }
+
+    // Writes a string that is followed by a trailing comment, which must not end up
+    // inside a quoteless or multiline string. At the root a string with a colon would
+    // be read as an object without braces, so it is written the same way, and so are
+    // chars, which have always been quoted.
+    fn serialize_quoted_str(&mut self, value: &str) -> Result<()> {
+        self.formatter.begin_inline(&mut self.writer, &self.path)?;
+        let result = self
+            .formatter
+            .write_string(&mut self.writer, &self.path, value);
+        self.formatter.end_inline(&mut self.writer, &self.path)?;
+        result
+    }
+
+    // Writes a value wrapped in `Commented` with its comments.
+    fn serialize_commented<T>(&mut self, comments: Comments, value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        if let (Some(comment), false) = (&comments.before, self.comment_written) {
+            self.write_comment(comment, false)?;
+        }
+        self.comment_written = false;
+        let quote_at = self.quote_at;
+        if comments.after.is_some() {
+            self.quote_at = Some(self.path.len());
+        }
+        let result = value.serialize(&mut *self);
+        self.quote_at = quote_at;
+        result?;
+        match comments.after {
+            Some(comment) => self.write_comment(&comment, true),
+            None => Ok(()),
+        }
+    }
+
+    // Writes a comment, which would end a value that is written on a single line.
+    fn write_comment(&mut self, comment: &str, trailing: bool) -> Result<()> {
+        if self.inline > 0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "comment on a single line").into());
+        }
+        self.formatter
+            .write_comment(&mut self.writer, &self.path, comment, trailing)
+    }
+
+    // Opens an array or object and remembers whether the members of the root object
+    // are written without braces, and so without indentation.
+    fn open(&mut self, ch: u8) -> Result<()> {
+        if self.path.is_empty() && self.inline == 0 {
+            self.braceless_root = ch == b'{' && !self.formatter.options().root_braces;
+        }
+        self.formatter.open(&mut self.writer, ch)
+    }
+
+    // Enters the documentation of an object member, or of an element without a
+    // name, and returns the comment above it. Nothing is entered without doc
+    // comments.
+    fn enter_doc(&mut self, name: Option<&str>) -> Option<String> {
+        let current = self.docs.last()?;
+        let (comment, child) = match (current, name) {
+            (Some(doc), Some(name)) => match doc.member(name) {
+                Some(member) => {
+                    let child = (member.doc)();
+                    (member.full_comment(child.as_ref()), child)
+                }
+                None => (None, doc.items.and_then(|items| items())),
+            },
+            (Some(doc), None) => (None, doc.items.and_then(|items| items())),
+            (None, _) => (None, None),
+        };
+        self.docs.push(child);
+        comment
+    }
+
+    fn leave_doc(&mut self) {
+        if self.docs.len() > 1 {
+            self.docs.pop();
+        }
+    }
+
+    // Writes a value, on a single line if it is an array or object that fits into
+    // `FormatOptions::max_width`. The value of an object member follows its key.
+    fn serialize_value<T>(&mut self, value: &T, key: Option<&str>) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        let options = self.formatter.options();
+        if self.inline > 0 || options.max_width.is_none() {
+            return value.serialize(&mut *self);
+        }
+        let braceless = self.path.is_empty() && !options.root_braces;
+        match probe(value).open {
+            Some(b'{') if braceless => return value.serialize(&mut *self),
+            Some(_) => {}
+            None => return value.serialize(&mut *self),
+        }
+        let depth = self.path.matches('/').count() - self.braceless_root as usize;
+        let mut column = depth * options.indent.chars().count();
+        if let Some(key) = key {
+            let mut counter = WidthCounter::new(usize::MAX);
+            write_key(&mut counter, options, key)?;
+            // the key is followed by ": "
+            column += counter.width + 2;
+        }
+        if !self.fits_inline(value, column) {
+            return value.serialize(&mut *self);
+        }
+        self.inline += 1;
+        self.formatter.begin_inline(&mut self.writer, &self.path)?;
+        let result = value.serialize(&mut *self);
+        self.formatter.end_inline(&mut self.writer, &self.path)?;
+        self.inline -= 1;
+        result
+    }
+
+    // Whether the value written on a single line fits behind the column.
+    fn fits_inline<T>(&self, value: &T, column: usize) -> bool
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        let options = self.formatter.options();
+        // a comma may follow the value
+        let column = column + options.commas as usize;
+        let room = match options.max_width {
+            Some(width) if width > column => width - column,
+            _ => return false,
+        };
+        let mut formatter = HjsonFormatter::with_options(options.clone());
+        formatter.inline = 1;
+        let mut ser = Serializer::with_formatter(WidthCounter::new(room), formatter);
+        ser.path = self.path.clone();
+        ser.policy = self.policy.clone();
+        ser.docs = self.docs.last().cloned().into_iter().collect();
+        ser.inline = 1;
+        value.serialize(&mut ser).is_ok()
+    }
+
+    // Opens the object that wraps the value of an enum variant. Its comment is
+    // already listed above the member that holds the enum.
+    fn begin_variant(&mut self, variant: &str) -> Result<()> {
+        self.open(b'{')?;
+        self.formatter.comma(&mut self.writer, true)?;
+        self.enter_doc(Some(variant));
+        self.push_path(variant);
+        self.writer.open_span(&self.path);
+        self.formatter
+            .write_key(&mut self.writer, &self.path, variant)?;
+        self.formatter.colon(&mut self.writer)?;
+        self.formatter
+            .begin_object_value(&mut self.writer, &self.path)
+    }
+
+    fn end_variant(&mut self) -> Result<()> {
+        self.writer.close_span();
+        self.formatter
+            .end_object_value(&mut self.writer, &self.path)?;
+        let len = self.path.rfind('/').unwrap_or(0);
+        self.path.truncate(len);
+        self.leave_doc();
+        self.formatter.close(&mut self.writer, b'}')
+    }
+}
+
+// Counts the characters written to it and fails once they exceed the limit.
+struct WidthCounter {
+    width: usize,
+    limit: usize,
+}
+
+impl WidthCounter {
+    fn new(limit: usize) -> Self {
+        WidthCounter { width: 0, limit }
+    }
+}
+
+impl io::Write for WidthCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // UTF-8 continuation bytes do not start a character
+        self.width += buf.iter().filter(|&&b| b & 0xc0 != 0x80).count();
+        if self.width > self.limit {
+            return Err(io::Error::new(io::ErrorKind::Other, "line too long"));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
@@ -61,6 +755,75 @@ pub enum State {
 pub struct Compound<'a, W, F> {
     ser: &'a mut Serializer<W, F>,
     state: State,
+    // the number of elements written so far
+    index: usize,
+    // the length of the container's path
+    path_len: usize,
+    // the key of the member whose value is serialized next
+    key: Option<String>,
+    // the members of an object with sorted keys, written when it is closed
+    sorted: Option<Vec<(String, Buffered)>>,
+}
+
+impl<'a, W, F> Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    fn serialize_member<T>(&mut self, key: &str, value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        self.ser.push_path(key);
+        let probe = probe(value);
+        let comment = match (
+            self.ser.enter_doc(Some(key)),
+            probe.comments.and_then(|comments| comments.before),
+        ) {
+            (Some(doc), Some(comment)) => Some(doc + "\n" + &comment),
+            (doc, comment) => doc.or(comment),
+        };
+        let comment = comment.as_deref();
+        let policy = &*self.ser.policy;
+        let skip = KeyPolicy::matches(&policy.skip, &self.ser.path, key)
+            || policy.skip_nulls && probe.null;
+        let redact = KeyPolicy::matches(&policy.redact, &self.ser.path, key);
+        if !skip {
+            match self.sorted {
+                Some(ref mut members) => {
+                    self.ser.writer.push_buffer();
+                    let result = self.ser.serialize_member(key, value, redact, comment);
+                    let buf = self.ser.writer.pop_buffer();
+                    result?;
+                    members.push((key.to_owned(), buf));
+                }
+                None => {
+                    self.ser
+                        .formatter
+                        .comma(&mut self.ser.writer, self.state == State::First)?;
+                    self.state = State::Rest;
+                    self.ser.serialize_member(key, value, redact, comment)?;
+                }
+            }
+        }
+        self.ser.leave_doc();
+        self.ser.path.truncate(self.path_len);
+        Ok(())
+    }
+
+    // Writes the members of an object with sorted keys.
+    fn write_sorted(&mut self) -> Result<()> {
+        let (Some(mut members), Some(compare)) = (self.sorted.take(), self.ser.policy.sort.clone())
+        else {
+            return Ok(());
+        };
+        members.sort_by(|a, b| compare(&a.0, &b.0));
+        for (i, (_, buf)) in members.iter().enumerate() {
+            self.ser.formatter.comma(&mut self.ser.writer, i == 0)?;
+            self.ser.writer.write_buffered(buf)?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a, W, F> ser::Serializer for &'a mut Serializer<W, F>
@@ -81,89 +844,96 @@ where
 
     #[inline]
     fn serialize_bool(self, value: bool) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        if value {
-            self.writer.write_all(b"true").map_err(From::from)
-        } else {
-            self.writer.write_all(b"false").map_err(From::from)
-        }
+        self.formatter
+            .write_bool(&mut self.writer, &self.path, value)
     }
 
     #[inline]
     fn serialize_i8(self, value: i8) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        write!(&mut self.writer, "{}", value).map_err(From::from)
+        self.serialize_integer(value)
     }
 
     #[inline]
     fn serialize_i16(self, value: i16) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        write!(&mut self.writer, "{}", value).map_err(From::from)
+        self.serialize_integer(value)
     }
 
     #[inline]
     fn serialize_i32(self, value: i32) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        write!(&mut self.writer, "{}", value).map_err(From::from)
+        self.serialize_integer(value)
     }
 
     #[inline]
     fn serialize_i64(self, value: i64) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        write!(&mut self.writer, "{}", value).map_err(From::from)
+        self.serialize_integer(value)
     }
 
     #[inline]
     fn serialize_u8(self, value: u8) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        write!(&mut self.writer, "{}", value).map_err(From::from)
+        self.serialize_integer(value)
     }
 
     #[inline]
     fn serialize_u16(self, value: u16) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        write!(&mut self.writer, "{}", value).map_err(From::from)
+        self.serialize_integer(value)
     }
 
     #[inline]
     fn serialize_u32(self, value: u32) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        write!(&mut self.writer, "{}", value).map_err(From::from)
+        self.serialize_integer(value)
     }
 
     #[inline]
     fn serialize_u64(self, value: u64) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        write!(&mut self.writer, "{}", value).map_err(From::from)
+        self.serialize_integer(value)
     }
 
     #[inline]
     fn serialize_f32(self, value: f32) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        fmt_f32_or_null(&mut self.writer, if value == -0f32 { 0f32 } else { value })
-            .map_err(From::from)
+        let value = if value == 0f32 { 0f32 } else { value };
+        self.serialize_float(value, value.is_finite())
     }
 
     #[inline]
     fn serialize_f64(self, value: f64) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        fmt_f64_or_null(&mut self.writer, if value == -0f64 { 0f64 } else { value })
-            .map_err(From::from)
+        let value = if value == 0f64 { 0f64 } else { value };
+        self.serialize_float(value, value.is_finite())
     }
 
     #[inline]
     fn serialize_char(self, value: char) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        escape_char(&mut self.writer, value).map_err(From::from)
+        self.serialize_quoted_str(value.encode_utf8(&mut [0; 4]))
     }
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result<()> {
-        quote_str(&mut self.writer, &mut self.formatter, value).map_err(From::from)
+        if self.quote_at == Some(self.path.len()) {
+            self.quote_at = None;
+            return self.serialize_quoted_str(value);
+        }
+        if self.path.is_empty() && value.contains(':') {
+            return self.serialize_quoted_str(value);
+        }
+        self.formatter
+            .write_string(&mut self.writer, &self.path, value)
     }
 
     #[inline]
     fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        let options = self.formatter.options();
+        let text = match options.bytes {
+            BytesFormat::Array => None,
+            BytesFormat::Base64 => Some(encode_base64(value)),
+            BytesFormat::Hex => Some(encode_hex(value)),
+        };
+        if let Some(mut text) = text {
+            if options.multiline != MultilinePolicy::Never {
+                text = wrap_lines(&text, 76);
+            }
+            return self
+                .formatter
+                .write_string(&mut self.writer, &self.path, &text);
+        }
         let mut seq = self.serialize_seq(Some(value.len()))?;
         for byte in value {
             ser::SerializeSeq::serialize_element(&mut seq, byte)?;
@@ -173,8 +943,7 @@ where
 
     #[inline]
     fn serialize_unit(self) -> Result<()> {
-        self.formatter.start_value(&mut self.writer)?;
-        self.writer.write_all(b"null").map_err(From::from)
+        self.formatter.write_null(&mut self.writer, &self.path)
     }
 
     #[inline]
@@ -194,10 +963,15 @@ where
 
     /// Serialize newtypes without an object wrapper.
     #[inline]
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + ser::Serialize,
     {
+        if name == commented::NAME {
+            if let Some(comments) = commented::take_pending() {
+                return self.serialize_commented(comments, value);
+            }
+        }
         value.serialize(self)
     }
 
@@ -212,12 +986,9 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
-        self.formatter.open(&mut self.writer, b'{')?;
-        self.formatter.comma(&mut self.writer, true)?;
-        escape_key(&mut self.writer, variant)?;
-        self.formatter.colon(&mut self.writer)?;
-        value.serialize(&mut *self)?;
-        self.formatter.close(&mut self.writer, b'}')
+        self.begin_variant(variant)?;
+        self.serialize_value(value, Some(variant))?;
+        self.end_variant()
     }
 
     #[inline]
@@ -236,14 +1007,22 @@ where
     #[inline]
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
         let state = if len == Some(0) {
-            self.formatter.start_value(&mut self.writer)?;
-            self.writer.write_all(b"[]")?;
+            self.formatter
+                .write_empty(&mut self.writer, &self.path, b'[')?;
             State::Empty
         } else {
-            self.formatter.open(&mut self.writer, b'[')?;
+            self.open(b'[')?;
             State::First
         };
-        Ok(Compound { ser: self, state })
+        let path_len = self.path.len();
+        Ok(Compound {
+            ser: self,
+            state,
+            index: 0,
+            path_len,
+            key: None,
+            sorted: None,
+        })
     }
 
     #[inline]
@@ -268,24 +1047,33 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.formatter.open(&mut self.writer, b'{')?;
-        self.formatter.comma(&mut self.writer, true)?;
-        escape_key(&mut self.writer, variant)?;
-        self.formatter.colon(&mut self.writer)?;
+        self.begin_variant(variant)?;
         self.serialize_seq(Some(len))
     }
 
     #[inline]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
         let state = if len == Some(0) {
-            self.formatter.start_value(&mut self.writer)?;
-            self.writer.write_all(b"{}")?;
+            self.formatter
+                .write_empty(&mut self.writer, &self.path, b'{')?;
             State::Empty
         } else {
-            self.formatter.open(&mut self.writer, b'{')?;
+            self.open(b'{')?;
             State::First
         };
-        Ok(Compound { ser: self, state })
+        let path_len = self.path.len();
+        let sorted = match self.policy.sort {
+            Some(_) if state == State::First => Some(Vec::new()),
+            _ => None,
+        };
+        Ok(Compound {
+            ser: self,
+            state,
+            index: 0,
+            path_len,
+            key: None,
+            sorted,
+        })
     }
 
     #[inline]
@@ -301,10 +1089,7 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        self.formatter.open(&mut self.writer, b'{')?;
-        self.formatter.comma(&mut self.writer, true)?;
-        escape_key(&mut self.writer, variant)?;
-        self.formatter.colon(&mut self.writer)?;
+        self.begin_variant(variant)?;
         self.serialize_map(Some(len))
     }
 }
@@ -325,7 +1110,15 @@ where
             .formatter
             .comma(&mut self.ser.writer, self.state == State::First)?;
         self.state = State::Rest;
-        value.serialize(&mut *self.ser)
+        self.ser.push_path(&self.index.to_string());
+        self.index += 1;
+        self.ser.enter_doc(None);
+        self.ser.writer.open_span(&self.ser.path);
+        self.ser.serialize_value(value, None)?;
+        self.ser.writer.close_span();
+        self.ser.leave_doc();
+        self.ser.path.truncate(self.path_len);
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok> {
@@ -396,7 +1189,7 @@ where
             State::Empty => {}
             _ => self.ser.formatter.close(&mut self.ser.writer, b']')?,
         }
-        self.ser.formatter.close(&mut self.ser.writer, b'}')
+        self.ser.end_variant()
     }
 }
 
@@ -412,27 +1205,33 @@ where
     where
         T: serde::Serialize + ?Sized,
     {
-        self.ser
-            .formatter
-            .comma(&mut self.ser.writer, self.state == State::First)?;
-        self.state = State::Rest;
-
-        key.serialize(MapKeySerializer { ser: self.ser })?;
-
-        self.ser.formatter.colon(&mut self.ser.writer)
+        self.key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: serde::Serialize + ?Sized,
     {
-        value.serialize(&mut *self.ser)
+        let key = self.key.take().unwrap_or_default();
+        self.serialize_member(&key, value)
     }
 
-    fn end(self) -> Result<Self::Ok> {
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<()>
+    where
+        K: serde::Serialize + ?Sized,
+        V: serde::Serialize + ?Sized,
+    {
+        self.serialize_member(&key.serialize(MapKeySerializer)?, value)
+    }
+
+    fn end(mut self) -> Result<Self::Ok> {
         match self.state {
             State::Empty => Ok(()),
-            _ => self.ser.formatter.close(&mut self.ser.writer, b'}'),
+            _ => {
+                self.write_sorted()?;
+                self.ser.formatter.close(&mut self.ser.writer, b'}')
+            }
         }
     }
 }
@@ -449,7 +1248,7 @@ where
     where
         T: serde::Serialize + ?Sized,
     {
-        ser::SerializeMap::serialize_entry(self, key, value)
+        self.serialize_member(key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
@@ -472,97 +1271,146 @@ where
         ser::SerializeStruct::serialize_field(self, key, value)
     }
 
-    fn end(self) -> Result<Self::Ok> {
+    fn end(mut self) -> Result<Self::Ok> {
         match self.state {
             State::Empty => {}
-            _ => self.ser.formatter.close(&mut self.ser.writer, b'}')?,
+            _ => {
+                self.write_sorted()?;
+                self.ser.formatter.close(&mut self.ser.writer, b'}')?
+            }
         }
-        self.ser.formatter.close(&mut self.ser.writer, b'}')
+        self.ser.end_variant()
     }
 }
 
-struct MapKeySerializer<'a, W: 'a, F: 'a> {
-    ser: &'a mut Serializer<W, F>,
+/// Writes the elements of an array one by one, see `Serializer::seq_writer`.
+pub struct SeqWriter<'a, W: 'a, F: 'a> {
+    compound: Compound<'a, W, F>,
 }
 
-impl<'a, W, F> ser::Serializer for MapKeySerializer<'a, W, F>
+impl<'a, W, F> SeqWriter<'a, W, F>
 where
     W: io::Write,
     F: Formatter,
 {
-    type Ok = ();
-    type Error = Error;
+    /// Serializes the next element straight to the writer.
+    pub fn write_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        ser::SerializeSeq::serialize_element(&mut self.compound, value)
+    }
 
-    #[inline]
-    fn serialize_str(self, value: &str) -> Result<()> {
-        escape_key(&mut self.ser.writer, value).map_err(From::from)
+    /// Closes the array.
+    pub fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self.compound)
     }
+}
 
-    type SerializeSeq = ser::Impossible<(), Error>;
-    type SerializeTuple = ser::Impossible<(), Error>;
-    type SerializeTupleStruct = ser::Impossible<(), Error>;
-    type SerializeTupleVariant = ser::Impossible<(), Error>;
-    type SerializeMap = ser::Impossible<(), Error>;
-    type SerializeStruct = ser::Impossible<(), Error>;
-    type SerializeStructVariant = ser::Impossible<(), Error>;
+/// Writes the members of an object one by one, see `Serializer::map_writer`.
+pub struct MapWriter<'a, W: 'a, F: 'a> {
+    compound: Compound<'a, W, F>,
+}
 
-    fn serialize_bool(self, _value: bool) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+impl<'a, W, F> MapWriter<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    /// Serializes the next member straight to the writer. Keys are converted like the keys
+    /// of maps.
+    pub fn write_entry<K, V>(&mut self, key: &K, value: &V) -> Result<()>
+    where
+        K: ?Sized + ser::Serialize,
+        V: ?Sized + ser::Serialize,
+    {
+        ser::SerializeMap::serialize_entry(&mut self.compound, key, value)
     }
 
-    fn serialize_i8(self, _value: i8) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+    /// Closes the object.
+    pub fn end(self) -> Result<()> {
+        ser::SerializeMap::end(self.compound)
     }
+}
 
-    fn serialize_i16(self, _value: i16) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
-    }
+// Turns a map key into the string it is written as. Like in JSON, integer,
+// boolean, char and unit variant keys are written as strings.
+struct MapKeySerializer;
 
-    fn serialize_i32(self, _value: i32) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<String> {
+        Ok(value.to_owned())
     }
 
-    fn serialize_i64(self, _value: i64) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+    type SerializeSeq = ser::Impossible<String, Error>;
+    type SerializeTuple = ser::Impossible<String, Error>;
+    type SerializeTupleStruct = ser::Impossible<String, Error>;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    fn serialize_bool(self, value: bool) -> Result<String> {
+        Ok(value.to_string())
     }
 
-    fn serialize_u8(self, _value: u8) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+    fn serialize_i8(self, value: i8) -> Result<String> {
+        Ok(value.to_string())
     }
 
-    fn serialize_u16(self, _value: u16) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+    fn serialize_i16(self, value: i16) -> Result<String> {
+        Ok(value.to_string())
     }
 
-    fn serialize_u32(self, _value: u32) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+    fn serialize_i32(self, value: i32) -> Result<String> {
+        Ok(value.to_string())
     }
 
-    fn serialize_u64(self, _value: u64) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+    fn serialize_i64(self, value: i64) -> Result<String> {
+        Ok(value.to_string())
     }
 
-    fn serialize_f32(self, _value: f32) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+    fn serialize_u8(self, value: u8) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<String> {
+        Ok(value.to_string())
     }
 
-    fn serialize_f64(self, _value: f64) -> Result<()> {
+    fn serialize_u64(self, value: u64) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<String> {
         Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
     }
 
-    fn serialize_char(self, _value: char) -> Result<()> {
+    fn serialize_f64(self, _value: f64) -> Result<String> {
         Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
     }
 
-    fn serialize_bytes(self, _value: &[u8]) -> Result<()> {
+    fn serialize_char(self, value: char) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<String> {
         Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
     }
 
-    fn serialize_unit(self) -> Result<()> {
+    fn serialize_unit(self) -> Result<String> {
         Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
     }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
         Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
     }
 
@@ -570,16 +1418,16 @@ where
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-    ) -> Result<()> {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_owned())
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<String>
     where
         T: ?Sized + ser::Serialize,
     {
-        Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
+        value.serialize(self)
     }
 
     fn serialize_newtype_variant<T>(
@@ -588,18 +1436,18 @@ where
         _variant_index: u32,
         _variant: &'static str,
         _value: &T,
-    ) -> Result<()>
+    ) -> Result<String>
     where
         T: ?Sized + ser::Serialize,
     {
         Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
     }
 
-    fn serialize_none(self) -> Result<()> {
+    fn serialize_none(self) -> Result<String> {
         Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0))
     }
 
-    fn serialize_some<T>(self, _value: &T) -> Result<()>
+    fn serialize_some<T>(self, _value: &T) -> Result<String>
     where
         T: ?Sized + ser::Serialize,
     {
@@ -651,7 +1499,307 @@ where
     }
 }
 
-/// This trait abstracts away serializing the JSON control characters
+// Finds out whether a value is written as `null` or as an array or object, and
+// takes its comments.
+fn probe<T>(value: &T) -> Probe
+where
+    T: ?Sized + ser::Serialize,
+{
+    value.serialize(ValueProbe).unwrap_or_else(|e| Probe {
+        open: e.open,
+        ..Probe::default()
+    })
+}
+
+#[derive(Default)]
+struct Probe {
+    null: bool,
+    // the bracket that opens an array or object
+    open: Option<u8>,
+    comments: Option<Comments>,
+}
+
+impl Probe {
+    fn null() -> Self {
+        Probe {
+            null: true,
+            ..Probe::default()
+        }
+    }
+}
+
+// Serializes a value to a `Probe`. Containers are never null and fail right
+// away, so their members are not visited.
+struct ValueProbe;
+
+#[derive(Debug)]
+struct NotNull {
+    open: Option<u8>,
+}
+
+impl std::fmt::Display for NotNull {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("not null")
+    }
+}
+
+impl std::error::Error for NotNull {}
+
+impl ser::Error for NotNull {
+    fn custom<T: Display>(_msg: T) -> Self {
+        NotNull { open: None }
+    }
+}
+
+impl ser::Serializer for ValueProbe {
+    type Ok = Probe;
+    type Error = NotNull;
+
+    type SerializeSeq = ser::Impossible<Probe, NotNull>;
+    type SerializeTuple = ser::Impossible<Probe, NotNull>;
+    type SerializeTupleStruct = ser::Impossible<Probe, NotNull>;
+    type SerializeTupleVariant = ser::Impossible<Probe, NotNull>;
+    type SerializeMap = ser::Impossible<Probe, NotNull>;
+    type SerializeStruct = ser::Impossible<Probe, NotNull>;
+    type SerializeStructVariant = ser::Impossible<Probe, NotNull>;
+
+    fn serialize_bool(self, _value: bool) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_i8(self, _value: i8) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_i16(self, _value: i16) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_i32(self, _value: i32) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_i64(self, _value: i64) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_u8(self, _value: u8) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_u16(self, _value: u16) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_u32(self, _value: u32) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_u64(self, _value: u64) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_f32(self, value: f32) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe {
+            null: !value.is_finite(),
+            ..Probe::default()
+        })
+    }
+
+    fn serialize_f64(self, value: f64) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe {
+            null: !value.is_finite(),
+            ..Probe::default()
+        })
+    }
+
+    fn serialize_char(self, _value: char) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_str(self, _value: &str) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_none(self) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::null())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> std::result::Result<Probe, NotNull>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::null())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> std::result::Result<Probe, NotNull> {
+        Ok(Probe::default())
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> std::result::Result<Probe, NotNull>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        if name == commented::NAME {
+            if let Some(comments) = commented::take_pending() {
+                return Ok(Probe {
+                    comments: Some(comments),
+                    ..probe(value)
+                });
+            }
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> std::result::Result<Probe, NotNull>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        Ok(Probe {
+            open: Some(b'{'),
+            ..Probe::default()
+        })
+    }
+
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> std::result::Result<Self::SerializeSeq, NotNull> {
+        Err(NotNull { open: Some(b'[') })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> std::result::Result<Self::SerializeTuple, NotNull> {
+        Err(NotNull { open: Some(b'[') })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeTupleStruct, NotNull> {
+        Err(NotNull { open: Some(b'[') })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeTupleVariant, NotNull> {
+        Err(NotNull { open: Some(b'{') })
+    }
+
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> std::result::Result<Self::SerializeMap, NotNull> {
+        Err(NotNull { open: Some(b'{') })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeStruct, NotNull> {
+        Err(NotNull { open: Some(b'{') })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> std::result::Result<Self::SerializeStructVariant, NotNull> {
+        Err(NotNull { open: Some(b'{') })
+    }
+}
+
+/// This trait abstracts away serializing the JSON control characters, keys and values.
+///
+/// The hooks for keys and values receive the JSON Pointer of the value they write, so a
+/// formatter can change the output for parts of a document:
+///
+/// ```rust
+/// use std::io;
+///
+/// use serde_hjson::ser::{escape_str, Formatter, HjsonFormatter, Serializer};
+///
+/// // Quotes all strings below /secrets.
+/// struct SecretFormatter(HjsonFormatter);
+///
+/// impl Formatter for SecretFormatter {
+///     fn open<W: io::Write>(&mut self, w: &mut W, ch: u8) -> serde_hjson::Result<()> {
+///         self.0.open(w, ch)
+///     }
+///     fn comma<W: io::Write>(&mut self, w: &mut W, first: bool) -> serde_hjson::Result<()> {
+///         self.0.comma(w, first)
+///     }
+///     fn colon<W: io::Write>(&mut self, w: &mut W) -> serde_hjson::Result<()> {
+///         self.0.colon(w)
+///     }
+///     fn close<W: io::Write>(&mut self, w: &mut W, ch: u8) -> serde_hjson::Result<()> {
+///         self.0.close(w, ch)
+///     }
+///     fn newline<W: io::Write>(&mut self, w: &mut W, add: i32) -> serde_hjson::Result<()> {
+///         self.0.newline(w, add)
+///     }
+///     fn start_value<W: io::Write>(&mut self, w: &mut W) -> serde_hjson::Result<()> {
+///         self.0.start_value(w)
+///     }
+///     fn write_string<W: io::Write>(
+///         &mut self,
+///         w: &mut W,
+///         path: &str,
+///         value: &str,
+///     ) -> serde_hjson::Result<()> {
+///         if path.starts_with("/secrets/") {
+///             self.start_value(w)?;
+///             escape_str(w, value)
+///         } else {
+///             self.0.write_string(w, path, value)
+///         }
+///     }
+/// }
+///
+/// let value: serde_hjson::Value =
+///     serde_hjson::from_str("{user: \"admin\", secrets: {token: \"abc\"}}").unwrap();
+/// let mut out = Vec::new();
+/// let mut ser = Serializer::with_formatter(&mut out, SecretFormatter(HjsonFormatter::new()));
+/// serde::Serialize::serialize(&value, &mut ser).unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "{\n  user: admin\n  secrets:\n  {\n    token: \"abc\"\n  }\n}"
+/// );
+/// ```
 pub trait Formatter {
     /// Called when serializing a '{' or '['.
     fn open<W>(&mut self, writer: &mut W, ch: u8) -> Result<()>
@@ -682,42 +1830,424 @@ pub trait Formatter {
     fn start_value<W>(&mut self, writer: &mut W) -> Result<()>
     where
         W: io::Write;
+
+    /// The options that decide how keys and strings are quoted and escaped.
+    fn options(&self) -> &FormatOptions {
+        &DEFAULT_OPTIONS
+    }
+
+    /// Called when serializing the key of an object member. `path` is the JSON Pointer of the
+    /// member, like `/server/port`.
+    fn write_key<W>(&mut self, writer: &mut W, path: &str, key: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = path;
+        write_key(writer, self.options(), key)
+    }
+
+    /// Called before the value of an object member, after its key and colon.
+    fn begin_object_value<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = (writer, path);
+        Ok(())
+    }
+
+    /// Called after the value of an object member.
+    fn end_object_value<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = (writer, path);
+        Ok(())
+    }
+
+    /// Called before a value at `path` that has to be written on a single line: a string that
+    /// a comment follows on the same line, or an array or object that fits into
+    /// `FormatOptions::max_width`. Strings inside it must be neither quoteless nor multiline.
+    fn begin_inline<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = (writer, path);
+        Ok(())
+    }
+
+    /// Called after the value that `begin_inline` was called for.
+    fn end_inline<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = (writer, path);
+        Ok(())
+    }
+
+    /// Called when serializing a string value at `path`.
+    fn write_string<W>(&mut self, writer: &mut W, path: &str, value: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = path;
+        quote_str(writer, self, value)
+    }
+
+    /// Called when serializing a number at `path`. `value` is the number formatted like in
+    /// JavaScript, non-finite floats are written with `write_null`.
+    fn write_number<W>(&mut self, writer: &mut W, path: &str, value: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = path;
+        self.start_value(writer)?;
+        writer.write_all(value.as_bytes()).map_err(From::from)
+    }
+
+    /// Called when serializing a boolean at `path`.
+    fn write_bool<W>(&mut self, writer: &mut W, path: &str, value: bool) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = path;
+        self.start_value(writer)?;
+        let text: &[u8] = if value { b"true" } else { b"false" };
+        writer.write_all(text).map_err(From::from)
+    }
+
+    /// Called when serializing `null` at `path`.
+    fn write_null<W>(&mut self, writer: &mut W, path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = path;
+        self.start_value(writer)?;
+        writer.write_all(b"null").map_err(From::from)
+    }
+
+    /// Called when serializing an empty array or object at `path`, where `ch` is its '[' or
+    /// '{'. It is written on one line instead of with `open` and `close`.
+    fn write_empty<W>(&mut self, writer: &mut W, path: &str, ch: u8) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = path;
+        self.start_value(writer)?;
+        let text: &[u8] = if ch == b'[' { b"[]" } else { b"{}" };
+        writer.write_all(text).map_err(From::from)
+    }
+
+    /// Called when writing the comment of a value wrapped in `Commented` at `path`. A
+    /// `trailing` comment follows the value on the same line, any other comment comes before
+    /// the key or value and has to end with a new line.
+    fn write_comment<W>(
+        &mut self,
+        writer: &mut W,
+        path: &str,
+        comment: &str,
+        trailing: bool,
+    ) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let _ = path;
+        for (i, line) in comment.lines().enumerate() {
+            if trailing && i > 0 {
+                self.newline(writer, 0)?;
+            } else if trailing {
+                writer.write_all(b" ")?;
+            }
+            writer.write_all(b"#")?;
+            if !line.is_empty() {
+                writer.write_all(b" ")?;
+                writer.write_all(line.as_bytes())?;
+            }
+            if !trailing {
+                self.newline(writer, 0)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How keys and string values are quoted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotePolicy {
+    /// Keys and strings are written without quotes whenever the syntax allows it.
+    Quoteless,
+    /// Keys are only quoted when they are empty or contain whitespace, punctuators, quotes or
+    /// comment markers, like with `Quoteless`, but strings are always quoted.
+    Minimal,
+    /// Keys and strings are always quoted.
+    Always,
+}
+
+/// When strings are written in the `'''` multiline format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MultilinePolicy {
+    /// For all strings that would otherwise need escapes, even single lines.
+    Auto,
+    /// Only for strings that contain line breaks.
+    LineBreaks,
+    /// Never, the strings are escaped instead.
+    Never,
+}
+
+/// How byte buffers are written.
+///
+/// This applies to types that serialize as bytes, like `serde_bytes::ByteBuf`. A `Vec<u8>`
+/// serializes as a sequence and is always written as an array. Encoded strings are only
+/// decoded when reading with a `Deserializer` or `value::BytesDeserializer` that is given
+/// the format, otherwise they give their UTF-8 bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BytesFormat {
+    /// An array of numbers.
+    #[default]
+    Array,
+    /// A base64 string with padding.
+    Base64,
+    /// A lowercase hex string.
+    Hex,
+}
+
+/// Options that control the output of the `HjsonFormatter`.
+///
+/// ```rust
+/// use serde_hjson::ser::{FormatOptions, QuotePolicy};
+///
+/// let options = FormatOptions::new()
+///     .indent("    ")
+///     .braces_same_line(true)
+///     .quotes(QuotePolicy::Always);
+/// let value: serde_hjson::Value = serde_hjson::from_str("{a: {b: \"text\"}}").unwrap();
+/// let text = serde_hjson::to_string_with_options(&value, &options).unwrap();
+/// assert_eq!(text, "{\n    \"a\": {\n        \"b\": \"text\"\n    }\n}");
+/// ```
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    indent: Cow<'static, str>,
+    braces_same_line: bool,
+    root_braces: bool,
+    crlf: bool,
+    ascii_only: bool,
+    quotes: QuotePolicy,
+    multiline: MultilinePolicy,
+    commas: bool,
+    max_width: Option<usize>,
+    bytes: BytesFormat,
+    float_point: bool,
 }
 
-struct HjsonFormatter<'a> {
+static DEFAULT_OPTIONS: FormatOptions = FormatOptions {
+    indent: Cow::Borrowed("  "),
+    braces_same_line: false,
+    root_braces: true,
+    crlf: false,
+    ascii_only: false,
+    quotes: QuotePolicy::Quoteless,
+    multiline: MultilinePolicy::Auto,
+    commas: false,
+    max_width: None,
+    bytes: BytesFormat::Array,
+    float_point: false,
+};
+
+impl FormatOptions {
+    /// Creates the default options: two spaces of indentation, braces on their own line,
+    /// braces around the root object, LF line endings, quoteless strings and no commas.
+    pub fn new() -> Self {
+        DEFAULT_OPTIONS.clone()
+    }
+
+    /// Sets the string used for one level of indentation.
+    pub fn indent(mut self, indent: &str) -> Self {
+        self.indent = Cow::Owned(indent.to_owned());
+        self
+    }
+
+    /// Puts opening braces on the same line as their key.
+    pub fn braces_same_line(mut self, same_line: bool) -> Self {
+        self.braces_same_line = same_line;
+        self
+    }
+
+    /// Writes the braces of the root object. Without them the members of the root object are
+    /// written without indentation.
+    pub fn root_braces(mut self, root_braces: bool) -> Self {
+        self.root_braces = root_braces;
+        self
+    }
+
+    /// Ends lines with CRLF instead of LF.
+    pub fn crlf(mut self, crlf: bool) -> Self {
+        self.crlf = crlf;
+        self
+    }
+
+    /// Escapes all non-ASCII characters, so the output is plain ASCII.
+    pub fn ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    /// Sets how keys and string values are quoted.
+    pub fn quotes(mut self, quotes: QuotePolicy) -> Self {
+        self.quotes = quotes;
+        self
+    }
+
+    /// Sets when strings are written in the `'''` multiline format.
+    pub fn multiline(mut self, multiline: MultilinePolicy) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
+    /// Writes commas between the members of objects and arrays. Since a quoteless string
+    /// would include the comma, all string values are quoted.
+    pub fn commas(mut self, commas: bool) -> Self {
+        self.commas = commas;
+        self
+    }
+
+    /// Writes arrays and objects on a single line, comma-separated, when they fit into
+    /// `width` characters including their indentation and key. Strings inside them are
+    /// quoted. Containers that do not fit are written one member per line.
+    ///
+    /// Nested arrays and objects are laid out by any `Serializer` whose formatter has this
+    /// option, the root value only by `to_string_with_options` and `to_writer_with_options`.
+    /// Containers with comments are never written on a single line.
+    ///
+    /// ```rust
+    /// use serde_hjson::ser::FormatOptions;
+    ///
+    /// let value: serde_hjson::Value =
+    ///     serde_hjson::from_str("{ports: [80, 443], name: \"web\"}").unwrap();
+    /// let options = FormatOptions::new().max_width(80);
+    /// let text = serde_hjson::to_string_with_options(&value, &options).unwrap();
+    /// assert_eq!(text, "{ports: [80, 443], name: \"web\"}");
+    /// ```
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    /// Sets how byte buffers, like `serde_bytes::ByteBuf`, are written. Encoded strings that
+    /// are longer than 76 characters are split into lines of a multiline string, unless
+    /// multiline strings are disabled.
+    pub fn bytes(mut self, bytes: BytesFormat) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    /// Writes floats with an integral value like `1.0` instead of `1`, so they are read back
+    /// as floats instead of integers. Other floats are always written like in JavaScript.
+    ///
+    /// ```rust
+    /// use serde_hjson::{FormatOptions, Value};
+    ///
+    /// let options = FormatOptions::new().float_point(true);
+    /// let text = serde_hjson::to_string_with_options(&[1.0, 2.5, 1e21], &options).unwrap();
+    /// assert_eq!(text, "[\n  1.0\n  2.5\n  1e+21\n]");
+    /// let value: Value = serde_hjson::from_str(&text).unwrap();
+    /// assert_eq!(value.pointer("/0"), Some(&Value::F64(1.0)));
+    /// ```
+    pub fn float_point(mut self, float_point: bool) -> Self {
+        self.float_point = float_point;
+        self
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions::new()
+    }
+}
+
+/// The formatter for Hjson output, configured with `FormatOptions`.
+pub struct HjsonFormatter {
     current_indent: usize,
     current_is_array: bool,
     stack: Vec<bool>,
     at_colon: bool,
-    indent: &'a [u8],
-    braces_same_line: bool,
+    braceless_root: bool,
+    // how many of the open containers are written on a single line
+    inline: usize,
+    options: FormatOptions,
+    inline_options: FormatOptions,
 }
 
-impl<'a> HjsonFormatter<'a> {
+impl HjsonFormatter {
     /// Construct a formatter that defaults to using two spaces for indentation.
     pub fn new() -> Self {
-        HjsonFormatter::with_indent(b"  ")
+        HjsonFormatter::with_options(FormatOptions::new())
     }
 
     /// Construct a formatter that uses the `indent` string for indentation.
-    pub fn with_indent(indent: &'a [u8]) -> Self {
+    pub fn with_indent(indent: &[u8]) -> Self {
+        HjsonFormatter::with_options(FormatOptions::new().indent(&String::from_utf8_lossy(indent)))
+    }
+
+    /// Construct a formatter with the given options.
+    pub fn with_options(options: FormatOptions) -> Self {
+        // a quoteless or multiline string would end the line
+        let inline_options = FormatOptions {
+            quotes: match options.quotes {
+                QuotePolicy::Quoteless => QuotePolicy::Minimal,
+                quotes => quotes,
+            },
+            multiline: MultilinePolicy::Never,
+            ..options.clone()
+        };
         HjsonFormatter {
             current_indent: 0,
             current_is_array: false,
             stack: Vec::new(),
             at_colon: false,
-            indent,
-            braces_same_line: false,
+            braceless_root: false,
+            inline: 0,
+            options,
+            inline_options,
         }
     }
+
+    fn eol<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: io::Write,
+    {
+        writer
+            .write_all(if self.options.crlf { b"\r\n" } else { b"\n" })
+            .map_err(From::from)
+    }
+
+    // whether the members of the root object are written without braces
+    fn in_braceless_root(&self) -> bool {
+        self.braceless_root && self.stack.len() == 1
+    }
+}
+
+impl Default for HjsonFormatter {
+    fn default() -> Self {
+        HjsonFormatter::new()
+    }
 }
 
-impl<'a> Formatter for HjsonFormatter<'a> {
+impl Formatter for HjsonFormatter {
     fn open<W>(&mut self, writer: &mut W, ch: u8) -> Result<()>
     where
         W: io::Write,
     {
-        if self.current_indent > 0 && !self.current_is_array && !self.braces_same_line {
+        if self.inline > 0 {
+            self.start_value(writer)?;
+            self.stack.push(self.current_is_array);
+            self.current_is_array = ch == b'[';
+            return writer.write_all(&[ch]).map_err(From::from);
+        }
+        if self.stack.is_empty() && ch == b'{' && !self.options.root_braces {
+            self.braceless_root = true;
+            self.stack.push(self.current_is_array);
+            return Ok(());
+        }
+        if !self.stack.is_empty() && !self.current_is_array && !self.options.braces_same_line {
             self.newline(writer, 0)?;
         } else {
             self.start_value(writer)?;
@@ -728,32 +2258,52 @@ impl<'a> Formatter for HjsonFormatter<'a> {
         writer.write_all(&[ch]).map_err(From::from)
     }
 
-    fn comma<W>(&mut self, writer: &mut W, _: bool) -> Result<()>
+    fn comma<W>(&mut self, writer: &mut W, first: bool) -> Result<()>
     where
         W: io::Write,
     {
-        writer.write_all(b"\n")?;
-        indent(writer, self.current_indent, self.indent)
+        if self.inline > 0 {
+            if !first {
+                writer.write_all(b", ")?;
+            }
+            return Ok(());
+        }
+        if !first && self.options.commas {
+            writer.write_all(b",")?;
+        }
+        if first && self.in_braceless_root() {
+            return Ok(());
+        }
+        self.eol(writer)?;
+        indent(writer, self.current_indent, self.options.indent.as_bytes())
     }
 
     fn colon<W>(&mut self, writer: &mut W) -> Result<()>
     where
         W: io::Write,
     {
-        self.at_colon = !self.braces_same_line;
-        writer
-            .write_all(if self.braces_same_line { b": " } else { b":" })
-            .map_err(From::from)
+        // the space is only written before a value on the same line
+        self.at_colon = true;
+        writer.write_all(b":").map_err(From::from)
     }
 
     fn close<W>(&mut self, writer: &mut W, ch: u8) -> Result<()>
     where
         W: io::Write,
     {
+        if self.inline > 0 {
+            self.current_is_array = self.stack.pop().unwrap();
+            return writer.write_all(&[ch]).map_err(From::from);
+        }
+        if self.in_braceless_root() {
+            self.braceless_root = false;
+            self.stack.pop();
+            return Ok(());
+        }
         self.current_indent -= 1;
         self.current_is_array = self.stack.pop().unwrap();
-        writer.write_all(b"\n")?;
-        indent(writer, self.current_indent, self.indent)?;
+        self.eol(writer)?;
+        indent(writer, self.current_indent, self.options.indent.as_bytes())?;
         writer.write_all(&[ch]).map_err(From::from)
     }
 
@@ -762,9 +2312,13 @@ impl<'a> Formatter for HjsonFormatter<'a> {
         W: io::Write,
     {
         self.at_colon = false;
-        writer.write_all(b"\n")?;
+        self.eol(writer)?;
         let ii = self.current_indent as i32 + add_indent;
-        indent(writer, if ii < 0 { 0 } else { ii as usize }, self.indent)
+        indent(
+            writer,
+            if ii < 0 { 0 } else { ii as usize },
+            self.options.indent.as_bytes(),
+        )
     }
 
     fn start_value<W>(&mut self, writer: &mut W) -> Result<()>
@@ -777,11 +2331,218 @@ impl<'a> Formatter for HjsonFormatter<'a> {
         }
         Ok(())
     }
+
+    fn begin_inline<W>(&mut self, _writer: &mut W, _path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.inline += 1;
+        Ok(())
+    }
+
+    fn end_inline<W>(&mut self, _writer: &mut W, _path: &str) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.inline -= 1;
+        Ok(())
+    }
+
+    fn options(&self) -> &FormatOptions {
+        if self.inline > 0 {
+            &self.inline_options
+        } else {
+            &self.options
+        }
+    }
+}
+
+static JSON_OPTIONS: FormatOptions = FormatOptions {
+    indent: Cow::Borrowed("  "),
+    braces_same_line: true,
+    root_braces: true,
+    crlf: false,
+    ascii_only: false,
+    quotes: QuotePolicy::Always,
+    multiline: MultilinePolicy::Never,
+    commas: true,
+    max_width: None,
+    bytes: BytesFormat::Array,
+    float_point: false,
+};
+
+/// A formatter for JSON output, either compact or pretty printed like `JSON.stringify` with
+/// an indent.
+///
+/// Numbers are written like in JavaScript, so integral floats have no `.0` suffix and
+/// non-finite floats are written as `null`.
+pub struct JsonFormatter {
+    current_indent: usize,
+    // whether the open containers have members
+    stack: Vec<bool>,
+    indent: Option<Vec<u8>>,
+}
+
+impl JsonFormatter {
+    /// Construct a formatter that writes everything on a single line.
+    pub fn compact() -> Self {
+        JsonFormatter {
+            current_indent: 0,
+            stack: Vec::new(),
+            indent: None,
+        }
+    }
+
+    /// Construct a formatter that writes every member on its own line, indented with two
+    /// spaces.
+    pub fn pretty() -> Self {
+        JsonFormatter::with_indent(b"  ")
+    }
+
+    /// Construct a pretty printing formatter that uses the `indent` string for indentation.
+    pub fn with_indent(indent: &[u8]) -> Self {
+        JsonFormatter {
+            current_indent: 0,
+            stack: Vec::new(),
+            indent: Some(indent.to_vec()),
+        }
+    }
+
+    fn newline_indent<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: io::Write,
+    {
+        match self.indent {
+            Some(ref s) => {
+                writer.write_all(b"\n")?;
+                indent(writer, self.current_indent, s)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Formatter for JsonFormatter {
+    fn open<W>(&mut self, writer: &mut W, ch: u8) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.current_indent += 1;
+        self.stack.push(false);
+        writer.write_all(&[ch]).map_err(From::from)
+    }
+
+    fn comma<W>(&mut self, writer: &mut W, first: bool) -> Result<()>
+    where
+        W: io::Write,
+    {
+        if let Some(members) = self.stack.last_mut() {
+            *members = true;
+        }
+        if !first {
+            writer.write_all(b",")?;
+        }
+        self.newline_indent(writer)
+    }
+
+    fn colon<W>(&mut self, writer: &mut W) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let colon: &[u8] = if self.indent.is_some() { b": " } else { b":" };
+        writer.write_all(colon).map_err(From::from)
+    }
+
+    fn close<W>(&mut self, writer: &mut W, ch: u8) -> Result<()>
+    where
+        W: io::Write,
+    {
+        self.current_indent -= 1;
+        if self.stack.pop() == Some(true) {
+            self.newline_indent(writer)?;
+        }
+        writer.write_all(&[ch]).map_err(From::from)
+    }
+
+    fn newline<W>(&mut self, writer: &mut W, add_indent: i32) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let ii = self.current_indent as i32 + add_indent;
+        match self.indent {
+            Some(ref s) => {
+                writer.write_all(b"\n")?;
+                indent(writer, if ii < 0 { 0 } else { ii as usize }, s)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn start_value<W>(&mut self, _writer: &mut W) -> Result<()>
+    where
+        W: io::Write,
+    {
+        Ok(())
+    }
+
+    fn options(&self) -> &FormatOptions {
+        &JSON_OPTIONS
+    }
+
+    fn write_comment<W>(
+        &mut self,
+        _writer: &mut W,
+        _path: &str,
+        _comment: &str,
+        _trailing: bool,
+    ) -> Result<()>
+    where
+        W: io::Write,
+    {
+        Ok(())
+    }
 }
 
 /// Serializes and escapes a `&[u8]` into a Hjson string.
+///
+/// Text is escaped like `escape_str`. Bytes that are not valid UTF-8 are written as they are.
 #[inline]
 pub fn escape_bytes<W>(wr: &mut W, bytes: &[u8]) -> Result<()>
+where
+    W: io::Write,
+{
+    match str::from_utf8(bytes) {
+        Ok(value) => escape_str(wr, value),
+        Err(_) => {
+            wr.write_all(b"\"")?;
+            let mut start = 0;
+            for (i, &byte) in bytes.iter().enumerate() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 || byte == 0x7f {
+                    wr.write_all(&bytes[start..i])?;
+                    escape_special(wr, byte as char)?;
+                    start = i + 1;
+                }
+            }
+            wr.write_all(&bytes[start..])?;
+            wr.write_all(b"\"")?;
+            Ok(())
+        }
+    }
+}
+
+/// Serializes and escapes a `&str` into a quoted Hjson string.
+///
+/// Quotes, backslashes, control characters and the other characters that the Hjson syntax
+/// does not allow in strings are escaped, non-BMP characters as UTF-16 surrogate pairs.
+pub fn escape_str<W>(wr: &mut W, value: &str) -> Result<()>
+where
+    W: io::Write,
+{
+    escape_text(wr, value, false)
+}
+
+// Like escape_str, optionally escaping all non-ASCII characters too.
+fn escape_text<W>(wr: &mut W, value: &str, ascii_only: bool) -> Result<()>
 where
     W: io::Write,
 {
@@ -789,35 +2550,190 @@ where
 
     let mut start = 0;
 
-    for (i, byte) in bytes.iter().enumerate() {
-        let escaped = match *byte {
-            b'"' => b"\\\"",
-            b'\\' => b"\\\\",
-            b'\x08' => b"\\b",
-            b'\x0c' => b"\\f",
-            b'\n' => b"\\n",
-            b'\r' => b"\\r",
-            b'\t' => b"\\t",
-            _ => {
-                continue;
+    for (i, ch) in value.char_indices() {
+        if ch == '"' || ch == '\\' || is_special(ch) || ascii_only && !ch.is_ascii() {
+            if start < i {
+                wr.write_all(&value.as_bytes()[start..i])?;
             }
-        };
+            escape_special(wr, ch)?;
+            start = i + ch.len_utf8();
+        }
+    }
+
+    if start != value.len() {
+        wr.write_all(&value.as_bytes()[start..])?;
+    }
+
+    wr.write_all(b"\"")?;
+    Ok(())
+}
 
-        if start < i {
-            wr.write_all(&bytes[start..i])?;
+// Writes the escape sequence for a single character.
+fn escape_special<W>(wr: &mut W, ch: char) -> Result<()>
+where
+    W: io::Write,
+{
+    let escaped: &[u8] = match ch {
+        '"' => b"\\\"",
+        '\\' => b"\\\\",
+        '\x08' => b"\\b",
+        '\x0c' => b"\\f",
+        '\n' => b"\\n",
+        '\r' => b"\\r",
+        '\t' => b"\\t",
+        _ => {
+            for unit in ch.encode_utf16(&mut [0; 2]) {
+                write!(wr, "\\u{:04x}", unit)?;
+            }
+            return Ok(());
         }
+    };
+    wr.write_all(escaped)?;
+    Ok(())
+}
 
-        wr.write_all(escaped)?;
+// Characters that have to be escaped, so they cannot appear in quoteless or
+// multiline strings.
+fn is_special(ch: char) -> bool {
+    match ch {
+        '\x00'..='\x1f'
+        | '\x7f'..='\u{9f}'
+        | '\u{ad}'
+        | '\u{600}'..='\u{604}'
+        | '\u{70f}'
+        | '\u{17b4}'
+        | '\u{17b5}'
+        | '\u{200c}'..='\u{200f}'
+        | '\u{2028}'..='\u{202f}'
+        | '\u{2060}'..='\u{206f}'
+        | '\u{feff}'
+        | '\u{fff0}'..='\u{ffff}' => true,
+        _ => false,
+    }
+}
 
-        start = i + 1;
+// What a string value needs to be written, collected in a single pass.
+struct StrScan {
+    // cannot be written as a quoteless string
+    needs_quotes: bool,
+    // cannot be written without escapes
+    needs_escape: bool,
+    // cannot be written as a multiline string
+    needs_escape_ml: bool,
+}
+
+impl StrScan {
+    fn new(value: &str) -> StrScan {
+        let starts_with_punctuator = match value.as_bytes().first() {
+            Some(b'"' | b'#' | b'{' | b'}' | b'[' | b']' | b':' | b',') => true,
+            _ => value.starts_with("'''") || value.starts_with("/*") || value.starts_with("//"),
+        };
+        let leading_space = value.starts_with(char::is_whitespace);
+        let mut scan = StrScan {
+            needs_quotes: starts_with_punctuator
+                || leading_space
+                || value.ends_with(char::is_whitespace),
+            needs_escape: false,
+            needs_escape_ml: false,
+        };
+
+        let mut single_line = true;
+        let mut quotes = 0;
+        for ch in value.chars() {
+            if ch == '\'' {
+                quotes += 1;
+                if quotes == 3 {
+                    scan.needs_escape_ml = true;
+                }
+                continue;
+            }
+            quotes = 0;
+            if ch == '"' || ch == '\\' {
+                scan.needs_escape = true;
+            } else if is_special(ch) {
+                scan.needs_quotes = true;
+                scan.needs_escape = true;
+                // a \r would be dropped when the multiline string is read
+                if ch == '\n' {
+                    single_line = false;
+                } else {
+                    scan.needs_escape_ml = true;
+                }
+            }
+        }
+        // and so would the leading whitespace of a single line, while a trailing
+        // quote would run into the closing quotes
+        if single_line && (leading_space || value.ends_with('\'')) {
+            scan.needs_escape_ml = true;
+        }
+        scan
     }
+}
 
-    if start != bytes.len() {
-        wr.write_all(&bytes[start..])?;
+// Whether the string starts with a keyword that is only followed by
+// whitespace or a comma, closing bracket or comment on the same line.
+fn starts_with_keyword(value: &str) -> bool {
+    let rest = ["true", "false", "null"]
+        .iter()
+        .find_map(|keyword| value.strip_prefix(keyword));
+    let Some(rest) = rest else {
+        return false;
+    };
+    let rest = rest.trim_start();
+    rest.is_empty()
+        || ([",", "]", "}", "#", "//", "/*"]
+            .iter()
+            .any(|punctuator| rest.starts_with(punctuator))
+            && !rest.contains('\n'))
+}
+
+// Whether a key has to be quoted because it is empty or contains whitespace,
+// special characters, punctuators, quotes or the start of a comment.
+fn key_needs_quotes(value: &str) -> bool {
+    let mut prev = '\0';
+    let mut quotes = 0;
+    for ch in value.chars() {
+        match ch {
+            ',' | '{' | '[' | '}' | ']' | ':' | '#' | '"' => return true,
+            '/' | '*' if prev == '/' => return true,
+            _ if ch.is_whitespace() || is_special(ch) => return true,
+            _ => {}
+        }
+        quotes = if ch == '\'' { quotes + 1 } else { 0 };
+        if quotes == 3 {
+            return true;
+        }
+        prev = ch;
     }
+    value.is_empty()
+}
 
-    wr.write_all(b"\"")?;
-    Ok(())
+// Matches a key or path against a pattern in which `*` stands for any
+// characters except `/`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // where the last `*` is in the pattern and how far it matches the text
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            if text[star_t] == b'/' {
+                return false;
+            }
+            star = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == b'*')
 }
 
 /// Serializes and escapes a `&str` into a Hjson string.
@@ -825,41 +2741,47 @@ where
 pub fn quote_str<W, F>(wr: &mut W, formatter: &mut F, value: &str) -> Result<()>
 where
     W: io::Write,
-    F: Formatter,
+    F: Formatter + ?Sized,
 {
-    lazy_static! {
-        // NEEDS_ESCAPE tests if the string can be written without escapes
-        static ref NEEDS_ESCAPE: Regex = Regex::new("[\\\\\"\x00-\x1f\x7f-\u{9f}\u{00ad}\u{0600}-\u{0604}\u{070f}\u{17b4}\u{17b5}\u{200c}-\u{200f}\u{2028}-\u{202f}\u{2060}-\u{206f}\u{feff}\u{fff0}-\u{ffff}]").unwrap();
-        // NEEDS_QUOTES tests if the string can be written as a quoteless string (includes needsEscape but without \\ and \")
-        static ref NEEDS_QUOTES: Regex = Regex::new("^\\s|^\"|^'''|^#|^/\\*|^//|^\\{|^\\}|^\\[|^\\]|^:|^,|\\s$|[\x00-\x1f\x7f-\u{9f}\u{00ad}\u{0600}-\u{0604}\u{070f}\u{17b4}\u{17b5}\u{200c}-\u{200f}\u{2028}-\u{202f}\u{2060}-\u{206f}\u{feff}\u{fff0}-\u{ffff}]").unwrap();
-        // NEEDS_ESCAPEML tests if the string can be written as a multiline string (includes needsEscape but without \n, \r, \\ and \")
-        static ref NEEDS_ESCAPEML: Regex = Regex::new("'''|[\x00-\x09\x0b\x0c\x0e-\x1f\x7f-\u{9f}\u{00ad}\u{0600}-\u{0604}\u{070f}\u{17b4}\u{17b5}\u{200c}-\u{200f}\u{2028}-\u{202f}\u{2060}-\u{206f}\u{feff}\u{fff0}-\u{ffff}]").unwrap();
-        // starts with a keyword and optionally is followed by a comment
-        static ref STARTS_WITH_KEYWORD: Regex = Regex::new(r#"^(true|false|null)\s*((,|\]|\}|#|//|/\*).*)?$"#).unwrap();
-    }
-
     if value.is_empty() {
         formatter.start_value(wr)?;
-        return escape_bytes(wr, value.as_bytes());
-    }
+        return escape_str(wr, value);
+    }
+
+    let options = formatter.options();
+    let ascii_only = options.ascii_only;
+    // non-ASCII characters can only be escaped in quoted strings
+    let escape_non_ascii = ascii_only && !value.is_ascii();
+    // with commas, a quoteless string would include the comma
+    let quoteless =
+        options.quotes == QuotePolicy::Quoteless && !options.commas && !escape_non_ascii;
+    let multiline = !escape_non_ascii
+        && match options.multiline {
+            MultilinePolicy::Auto => true,
+            MultilinePolicy::LineBreaks => value.contains('\n'),
+            MultilinePolicy::Never => false,
+        };
 
     // Check if we can insert this string without quotes
     // see hjson syntax (must not parse as true, false, null or number)
 
-    let mut pn = ParseNumber::new(value.bytes());
-    let is_number = pn.parse(true).is_ok();
+    let scan = StrScan::new(value);
+    let is_number = match value.as_bytes()[0] {
+        b'-' | b'0'..=b'9' => ParseNumber::new(value.bytes()).parse(true).is_ok(),
+        _ => false,
+    };
 
-    if is_number || NEEDS_QUOTES.is_match(value) || STARTS_WITH_KEYWORD.is_match(value) {
+    if !quoteless || is_number || scan.needs_quotes || starts_with_keyword(value) {
         // First check if the string can be expressed in multiline format or
         // we must replace the offending characters with safe escape sequences.
 
-        if NEEDS_ESCAPE.is_match(value) && !NEEDS_ESCAPEML.is_match(value)
+        if multiline && scan.needs_escape && !scan.needs_escape_ml
         /* && !isRootObject */
         {
             ml_str(wr, formatter, value)
         } else {
             formatter.start_value(wr)?;
-            escape_bytes(wr, value.as_bytes())
+            escape_text(wr, value, ascii_only)
         }
     } else {
         // without quotes
@@ -872,7 +2794,7 @@ where
 pub fn ml_str<W, F>(wr: &mut W, formatter: &mut F, value: &str) -> Result<()>
 where
     W: io::Write,
-    F: Formatter,
+    F: Formatter + ?Sized,
 {
     // wrap the string into the ''' (multiline) format
 
@@ -905,53 +2827,40 @@ pub fn escape_key<W>(wr: &mut W, value: &str) -> Result<()>
 where
     W: io::Write,
 {
-    lazy_static! {
-        static ref NEEDS_ESCAPE_NAME: Regex =
-            Regex::new(r#"[,\{\[\}\]\s:#"]|//|/\*|'''|^$"#).unwrap();
-    }
-
     // Check if we can insert this name without quotes
-    if NEEDS_ESCAPE_NAME.is_match(value) {
-        escape_bytes(wr, value.as_bytes()).map_err(From::from)
+    if key_needs_quotes(value) {
+        escape_str(wr, value)
     } else {
         wr.write_all(value.as_bytes()).map_err(From::from)
     }
 }
 
-#[inline]
-fn escape_char<W>(wr: &mut W, value: char) -> Result<()>
+// Writes a key as the formatter's options require.
+fn write_key<W>(wr: &mut W, options: &FormatOptions, value: &str) -> Result<()>
 where
     W: io::Write,
 {
-    // FIXME: this allocation is required in order to be compatible with stable
-    // rust, which doesn't support encoding a `char` into a stack buffer.
-    let mut s = String::new();
-    s.push(value);
-    escape_bytes(wr, s.as_bytes())
-}
-
-fn fmt_f32_or_null<W>(wr: &mut W, value: f32) -> Result<()>
-where
-    W: io::Write,
-{
-    match value.classify() {
-        FpCategory::Nan | FpCategory::Infinite => wr.write_all(b"null")?,
-        _ => wr.write_all(fmt_small(value).as_bytes())?,
+    let quoted = match options.quotes {
+        QuotePolicy::Always => true,
+        // a key ends at its colon, so unlike a string value it only needs
+        // quotes for characters that the syntax does not allow in it
+        QuotePolicy::Minimal | QuotePolicy::Quoteless => key_needs_quotes(value),
+    };
+    if quoted || options.ascii_only && !value.is_ascii() {
+        escape_text(wr, value, options.ascii_only)
+    } else {
+        wr.write_all(value.as_bytes()).map_err(From::from)
     }
-
-    Ok(())
 }
 
-fn fmt_f64_or_null<W>(wr: &mut W, value: f64) -> Result<()>
-where
-    W: io::Write,
-{
-    match value.classify() {
-        FpCategory::Nan | FpCategory::Infinite => wr.write_all(b"null")?,
-        _ => wr.write_all(fmt_small(value).as_bytes())?,
-    }
-
-    Ok(())
+// Splits ASCII text into lines of the given length.
+fn wrap_lines(text: &str, width: usize) -> String {
+    let lines: Vec<&str> = text
+        .as_bytes()
+        .chunks(width)
+        .map(|line| str::from_utf8(line).unwrap())
+        .collect();
+    lines.join("\n")
 }
 
 fn indent<W>(wr: &mut W, n: usize, s: &[u8]) -> Result<()>
@@ -965,20 +2874,43 @@ where
     Ok(())
 }
 
-// format similar to es6
+// format like Number.prototype.toString in es6, from the shortest digits
+// that represent the value
 fn fmt_small<N>(value: N) -> String
 where
-    N: Display + LowerExp,
+    N: ryu::Float,
 {
-    let f1 = format!("{}", value);
-    let f2 = format!("{:e}", value);
-    if f1.len() <= f2.len() + 1 {
-        f1
-    } else if !f2.contains("e-") {
-        f2.replace("e", "e+")
+    let mut buffer = ryu::Buffer::new();
+    let text = buffer.format_finite(value);
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => ("-", text),
+        None => ("", text),
+    };
+    let (mantissa, e) = text.split_once('e').unwrap_or((text, "0"));
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac);
+    let leading = digits.len() - digits.trim_start_matches('0').len();
+    let digits = digits.trim_matches('0');
+    if digits.is_empty() {
+        return "0".to_owned();
+    }
+    let k = digits.len() as i32;
+    // the position of the decimal point relative to the digits
+    let n = int.len() as i32 - leading as i32 + e.parse::<i32>().unwrap_or(0);
+
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
     } else {
-        f2
-    }
+        let (first, rest) = digits.split_at(1);
+        let point = if rest.is_empty() { "" } else { "." };
+        let exp_sign = if n > 0 { "+" } else { "-" };
+        format!("{}{}{}e{}{}", first, point, rest, exp_sign, (n - 1).abs())
+    };
+    format!("{}{}", sign, body)
 }
 
 /// Encode the specified struct into a Hjson `[u8]` writer.
@@ -993,6 +2925,43 @@ where
     Ok(())
 }
 
+/// Encode the specified struct into a Hjson `[u8]` writer and return where each value was
+/// written.
+///
+/// ```rust
+/// let mut writer = Vec::new();
+/// let map = serde_hjson::to_writer_with_source_map(&mut writer, &vec![1, 23]).unwrap();
+/// let span = map.get("/1").unwrap();
+/// assert_eq!((span.line, span.column, span.end_column), (3, 3, 5));
+/// assert_eq!(&writer[span.start..span.end], b"23");
+/// ```
+#[inline]
+pub fn to_writer_with_source_map<W, T>(writer: &mut W, value: &T) -> Result<SourceMap>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    let mut ser = Serializer::new(writer).record_source_map(true);
+    value.serialize(&mut ser)?;
+    Ok(ser.source_map().unwrap_or_default())
+}
+
+/// Encode the specified struct into a Hjson `[u8]` writer, formatted with the given options.
+#[inline]
+pub fn to_writer_with_options<W, T>(
+    writer: &mut W,
+    value: &T,
+    options: &FormatOptions,
+) -> Result<()>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    let formatter = HjsonFormatter::with_options(options.clone());
+    let mut ser = Serializer::with_formatter(writer, formatter);
+    ser.serialize_value(value, None)
+}
+
 /// Encode the specified struct into a Hjson `[u8]` buffer.
 #[inline]
 pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
@@ -1007,6 +2976,9 @@ where
 }
 
 /// Encode the specified struct into a Hjson `String` buffer.
+///
+/// A `Value` number can be read back as another variant, e.g. `Value::I64(1)` as
+/// `Value::U64(1)` or `Value::F64(2.0)` as `Value::U64(2)`, see `Value`.
 #[inline]
 pub fn to_string<T>(value: &T) -> Result<String>
 where
@@ -1016,3 +2988,70 @@ where
     let string = String::from_utf8(vec)?;
     Ok(string)
 }
+
+/// Encode the specified struct into a Hjson `String` buffer, formatted with the given options.
+#[inline]
+pub fn to_string_with_options<T>(value: &T, options: &FormatOptions) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    let mut writer = Vec::with_capacity(128);
+    to_writer_with_options(&mut writer, value, options)?;
+    let string = String::from_utf8(writer)?;
+    Ok(string)
+}
+
+/// Encode the specified struct into a Hjson `String` with the doc comments of its type, see
+/// `HjsonDoc`. The comment of the type is written at the top.
+#[inline]
+pub fn to_string_documented<T>(value: &T) -> Result<String>
+where
+    T: ser::Serialize + HjsonDoc,
+{
+    let doc = T::hjson_doc();
+    let root = Commented {
+        value,
+        comment: doc.comment.map(str::to_owned),
+        trailing: None,
+    };
+    let mut writer = Vec::with_capacity(128);
+    let mut ser = Serializer::new(&mut writer).doc_comments(doc);
+    ser::Serialize::serialize(&root, &mut ser)?;
+    let string = String::from_utf8(writer)?;
+    Ok(string)
+}
+
+/// Encode the specified struct into a compact JSON `String`.
+///
+/// ```rust
+/// let value: serde_hjson::Value = serde_hjson::from_str("{a: 1.0, b: [\"text\"]}").unwrap();
+/// let json = serde_hjson::to_json_string(&value).unwrap();
+/// assert_eq!(json, r#"{"a":1,"b":["text"]}"#);
+/// ```
+#[inline]
+pub fn to_json_string<T>(value: &T) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    to_string_with_formatter(value, JsonFormatter::compact())
+}
+
+/// Encode the specified struct into a pretty printed JSON `String`, indented with two spaces.
+#[inline]
+pub fn to_json_string_pretty<T>(value: &T) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    to_string_with_formatter(value, JsonFormatter::pretty())
+}
+
+fn to_string_with_formatter<T, F>(value: &T, formatter: F) -> Result<String>
+where
+    T: ser::Serialize,
+    F: Formatter,
+{
+    let mut writer = Vec::with_capacity(128);
+    value.serialize(&mut Serializer::with_formatter(&mut writer, formatter))?;
+    let string = String::from_utf8(writer)?;
+    Ok(string)
+}
@@ -1,67 +1,179 @@
-use std::io;
 use std::str;
 
+use serde::de;
+
 use super::error::{Error, ErrorCode, Result};
+use super::ser::BytesFormat;
+
+// how many bytes are buffered at once from iterators that know their length
+const CHUNK: usize = 8192;
+
+/// The input of a `Reader`: any `Iterator<Item = u8>`, or a `SliceInput` for text that is
+/// already in memory.
+pub trait Input {
+    /// Appends at most `max` more bytes to `buf` and returns false at the end of the input.
+    fn fill_buf(&mut self, buf: &mut Vec<u8>, max: usize) -> bool;
+
+    /// Returns the whole input if it is in memory, so that it can be read in place.
+    fn as_slice(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl<Iter> Input for Iter
+where
+    Iter: Iterator<Item = u8>,
+{
+    // Only as many bytes as the iterator promises to have are read, so
+    // streams that block are never asked for more than the parser needs.
+    fn fill_buf(&mut self, buf: &mut Vec<u8>, max: usize) -> bool {
+        let want = self.size_hint().0.clamp(1, max);
+        let len = buf.len();
+        buf.extend(self.by_ref().take(want));
+        buf.len() > len
+    }
+}
+
+/// Input from a byte slice, which is scanned in place without being copied.
+#[derive(Clone, Copy, Debug)]
+pub struct SliceInput<'a> {
+    slice: &'a [u8],
+}
 
-pub struct StringReader<Iter: Iterator<Item = u8>> {
-    iter: Iter,
+impl<'a> SliceInput<'a> {
+    /// Creates the input from a byte slice.
+    pub fn new(slice: &'a [u8]) -> SliceInput<'a> {
+        SliceInput { slice }
+    }
+}
+
+impl<'a> Input for SliceInput<'a> {
+    fn fill_buf(&mut self, _buf: &mut Vec<u8>, _max: usize) -> bool {
+        false
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self.slice)
+    }
+}
+
+// Reads bytes from the input and hands them out from a cursor. Bytes before the
+// cursor have been consumed, the rest is lookahead. Whitespace, comments and
+// string contents are scanned in bulk on the lookahead slice, which is the
+// input itself for a `SliceInput` and a buffer of bytes read from iterators.
+pub struct StringReader<Iter: Input> {
+    input: Iter,
     line: usize,
     col: usize,
-    ch: Vec<u8>,
+    offset: usize,
+    buf: Vec<u8>,
+    pos: usize,
+    hit_eof: bool,
 }
 
 impl<Iter> StringReader<Iter>
 where
-    Iter: Iterator<Item = u8>,
+    Iter: Input,
 {
     #[inline]
-    pub fn new(iter: Iter) -> Self {
+    pub fn new(input: Iter) -> Self {
         StringReader {
-            iter,
+            input,
             line: 1,
             col: 0,
-            ch: Vec::new(),
+            offset: 0,
+            buf: Vec::new(),
+            pos: 0,
+            hit_eof: false,
+        }
+    }
+
+    // the input read so far, of which `pos` bytes are consumed
+    #[inline]
+    fn data(&self) -> &[u8] {
+        match self.input.as_slice() {
+            Some(slice) => slice,
+            None => &self.buf,
         }
     }
 
-    fn next(&mut self) -> Option<io::Result<u8>> {
-        match self.iter.next() {
-            None => None,
-            Some(b'\n') => {
-                self.line += 1;
-                self.col = 0;
-                Some(Ok(b'\n'))
+    // Buffers more input, returns false at the end of the input.
+    fn fill(&mut self) -> bool {
+        if self.input.as_slice().is_none() {
+            if self.pos == self.buf.len() {
+                self.buf.clear();
+                self.pos = 0;
+            } else if self.pos >= CHUNK {
+                self.buf.drain(..self.pos);
+                self.pos = 0;
             }
-            Some(c) => {
-                self.col += 1;
-                Some(Ok(c))
+            if self.input.fill_buf(&mut self.buf, CHUNK) {
+                return true;
             }
         }
+        self.hit_eof = true;
+        false
     }
 
-    pub fn pos(&mut self) -> (usize, usize) {
+    // the lookahead, filled with at least one byte unless at the end
+    fn lookahead(&mut self) -> &[u8] {
+        if self.pos == self.data().len() {
+            self.fill();
+        }
+        let pos = self.pos;
+        &self.data()[pos..]
+    }
+
+    /// Continues position tracking from an earlier part of the input.
+    pub fn resume(&mut self, line: usize, col: usize, offset: usize) {
+        self.line = line;
+        self.col = col;
+        self.offset = offset;
+    }
+
+    /// Whether the end of the input has been looked at.
+    pub fn hit_eof(&self) -> bool {
+        self.hit_eof
+    }
+
+    // line and column are tracked for consumed characters only, so lookahead
+    // does not move the reported position
+    fn consume(&mut self, n: usize) {
+        let bytes = &self.data()[self.pos..self.pos + n];
+        match memchr::memrchr(b'\n', bytes) {
+            Some(last) => {
+                let lines = memchr::memchr_iter(b'\n', bytes).count();
+                self.line += lines;
+                self.col = n - last - 1;
+            }
+            None => self.col += n,
+        }
+        self.offset += n;
+        self.pos += n;
+    }
+
+    pub fn pos(&self) -> (usize, usize) {
         (self.line, self.col)
     }
 
+    /// Number of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     pub fn eof(&mut self) -> Result<bool> {
         Ok(self.peek()?.is_none())
     }
 
     pub fn peek_next(&mut self, idx: usize) -> Result<Option<u8>> {
-        while self.ch.len() <= idx {
-            match self.next() {
-                Some(Err(err)) => return Err(Error::Io(err)),
-                Some(Ok(ch)) => self.ch.push(ch),
-                None => return Ok(None),
+        while self.data().len() - self.pos <= idx {
+            if !self.fill() {
+                return Ok(None);
             }
         }
-        Ok(Some(self.ch[idx]))
+        Ok(Some(self.data()[self.pos + idx]))
     }
 
-    // pub fn peek_next_or_null(&mut self, idx: usize) -> Result<u8> {
-    //     Ok(try!(self.peek_next(idx)).unwrap_or(b'\x00'))
-    // }
-
     pub fn peek(&mut self) -> Result<Option<u8>> {
         self.peek_next(0)
     }
@@ -71,8 +183,10 @@ where
     }
 
     pub fn eat_char(&mut self) -> u8 {
-        if !self.ch.is_empty() {
-            self.ch.remove(0)
+        if self.pos < self.data().len() {
+            let ch = self.data()[self.pos];
+            self.consume(1);
+            ch
         // Fallback mechanism for consuming a character.
         // Sets ch to NULL termination
         } else {
@@ -80,44 +194,67 @@ where
         }
     }
 
-    pub fn uneat_char(&mut self, ch: u8) {
-        self.ch.insert(0, ch);
-    }
-
     pub fn next_char(&mut self) -> Result<Option<u8>> {
-        match self.ch.first() {
-            Some(&ch) => {
-                self.eat_char();
-                Ok(Some(ch))
-            }
-            None => match self.next() {
-                Some(Err(err)) => Err(Error::Io(err)),
-                Some(Ok(ch)) => Ok(Some(ch)),
-                None => Ok(None),
-            },
+        let ch = self.peek()?;
+        if ch.is_some() {
+            self.consume(1);
         }
+        Ok(ch)
     }
 
     pub fn next_char_or_null(&mut self) -> Result<u8> {
         Ok(self.next_char()?.unwrap_or(b'\x00'))
     }
 
-    fn eat_line(&mut self) -> Result<()> {
+    /// Consumes spaces, tabs and line breaks.
+    pub fn skip_blank(&mut self) {
         loop {
-            match self.peek()? {
-                Some(b'\n') | None => return Ok(()),
-                _ => {}
+            let blank = self
+                .lookahead()
+                .iter()
+                .position(|&ch| !matches!(ch, b' ' | b'\n' | b'\t' | b'\r'));
+            match blank {
+                Some(n) => return self.consume(n),
+                None if self.pos == self.data().len() => return,
+                None => self.consume(self.data().len() - self.pos),
             }
-            self.eat_char();
         }
     }
 
+    /// Consumes input up to, but not including, the first of the given bytes and returns it,
+    /// or `None` at the end of the input. The consumed bytes are appended to `out`.
+    pub fn read_until(&mut self, needles: &[u8], mut out: Option<&mut Vec<u8>>) -> Option<u8> {
+        loop {
+            let bytes = self.lookahead();
+            if bytes.is_empty() {
+                return None;
+            }
+            let found = match *needles {
+                [a] => memchr::memchr(a, bytes),
+                [a, b] => memchr::memchr2(a, b, bytes),
+                [a, b, c] => memchr::memchr3(a, b, c, bytes),
+                _ => bytes.iter().position(|ch| needles.contains(ch)),
+            };
+            let n = found.unwrap_or(bytes.len());
+            if let Some(out) = out.as_mut() {
+                out.extend_from_slice(&bytes[..n]);
+            }
+            self.consume(n);
+            if found.is_some() {
+                return Some(self.data()[self.pos]);
+            }
+        }
+    }
+
+    fn eat_line(&mut self) -> Result<()> {
+        self.read_until(b"\n", None);
+        Ok(())
+    }
+
     pub fn parse_whitespace(&mut self) -> Result<()> {
         loop {
+            self.skip_blank();
             match self.peek_or_null()? {
-                b' ' | b'\n' | b'\t' | b'\r' => {
-                    self.eat_char();
-                }
                 b'#' => self.eat_line()?,
                 b'/' => {
                     match self.peek_next(1)? {
@@ -125,8 +262,8 @@ where
                         Some(b'*') => {
                             self.eat_char();
                             self.eat_char();
-                            while !(self.peek()?.unwrap_or(b'*') == b'*'
-                                && self.peek_next(1)?.unwrap_or(b'/') == b'/')
+                            while self.read_until(b"*", None).is_some()
+                                && self.peek_next(1)? != Some(b'/')
                             {
                                 self.eat_char();
                             }
@@ -146,18 +283,23 @@ where
         }
     }
 
-    pub fn error(&mut self, reason: ErrorCode) -> Error {
+    pub fn error(&self, reason: ErrorCode) -> Error {
         Error::Syntax(reason, self.line, self.col)
     }
 }
 
+/// A number as it was read from Hjson text.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Number {
+    /// A negative integer.
     I64(i64),
+    /// A non-negative integer.
     U64(u64),
+    /// A floating point number.
     F64(f64),
 }
 
-pub struct ParseNumber<Iter: Iterator<Item = u8>> {
+pub struct ParseNumber<Iter: Input> {
     rdr: StringReader<Iter>,
     result: Vec<u8>,
 }
@@ -171,7 +313,7 @@ pub struct ParseNumber<Iter: Iterator<Item = u8>> {
 //     }
 // }
 
-impl<Iter: Iterator<Item = u8>> ParseNumber<Iter> {
+impl<Iter: Input> ParseNumber<Iter> {
     #[inline]
     pub fn new(iter: Iter) -> Self {
         ParseNumber {
@@ -212,22 +354,21 @@ impl<Iter: Iterator<Item = u8>> ParseNumber<Iter> {
                             }
                         }
 
-                        if is_float {
-                            Ok(Number::F64(res.parse::<f64>().map_err(|_| {
-                                let pos = self.rdr.pos();
-                                Error::Syntax(ErrorCode::InvalidNumber, pos.0, pos.1)
-                            })?))
-                        } else if res.starts_with('-') {
-                            Ok(Number::I64(res.parse::<i64>().map_err(|_| {
-                                let pos = self.rdr.pos();
-                                Error::Syntax(ErrorCode::InvalidNumber, pos.0, pos.1)
-                            })?))
-                        } else {
-                            Ok(Number::U64(res.parse::<u64>().map_err(|_| {
-                                let pos = self.rdr.pos();
-                                Error::Syntax(ErrorCode::InvalidNumber, pos.0, pos.1)
-                            })?))
+                        // an integer that does not fit is read as a float, like
+                        // the integral floats that are written without a fraction
+                        if !is_float {
+                            if res.starts_with('-') {
+                                if let Ok(n) = res.parse::<i64>() {
+                                    return Ok(Number::I64(n));
+                                }
+                            } else if let Ok(n) = res.parse::<u64>() {
+                                return Ok(Number::U64(n));
+                            }
                         }
+                        Ok(Number::F64(res.parse::<f64>().map_err(|_| {
+                            let pos = self.rdr.pos();
+                            Error::Syntax(ErrorCode::InvalidNumber, pos.0, pos.1)
+                        })?))
                     }
                     _ => Err(Error::Syntax(ErrorCode::InvalidNumber, 0, 0)),
                 }
@@ -335,3 +476,197 @@ impl<Iter: Iterator<Item = u8>> ParseNumber<Iter> {
         Ok(())
     }
 }
+
+/// Parses an array index of a JSON Pointer, rejecting leading zeros and signs.
+pub fn parse_index(s: &str) -> Option<usize> {
+    if s.starts_with('+') || (s.starts_with('0') && s.len() != 1) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Deserializes an object key into the type of a map key. Keys are always strings in Hjson,
+/// so numbers and booleans are parsed from the key's text.
+pub struct MapKeyDeserializer<'a> {
+    key: &'a str,
+}
+
+impl<'a> MapKeyDeserializer<'a> {
+    pub fn new(key: &'a str) -> Self {
+        MapKeyDeserializer { key }
+    }
+}
+
+// Parses the key into the requested type, or hands it to the visitor as a
+// string so that it reports the mismatch.
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                match self.key.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => visitor.visit_str(self.key),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for MapKeyDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_str(self.key)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_enum(de::IntoDeserializer::<Error>::into_deserializer(self.key))
+    }
+
+    serde::forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+const BASE64_DIGITS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes as base64 with padding.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_DIGITS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes base64 with or without padding, ignoring whitespace.
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut n, mut bits) = (0u32, 0);
+    let mut padding = 0;
+    for ch in text.bytes() {
+        let digit = match ch {
+            b'A'..=b'Z' => ch - b'A',
+            b'a'..=b'z' => ch - b'a' + 26,
+            b'0'..=b'9' => ch - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => {
+                padding += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        if padding > 0 {
+            return None;
+        }
+        n = n << 6 | digit as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    // a single digit left over cannot encode a byte
+    if bits >= 6 || padding > 2 {
+        return None;
+    }
+    Some(out)
+}
+
+/// Encodes bytes as lowercase hex.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(DIGITS[(b >> 4) as usize] as char);
+        out.push(DIGITS[(b & 0xf) as usize] as char);
+    }
+    out
+}
+
+/// Decodes hex in either case, ignoring whitespace.
+pub fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 2);
+    let mut high = None;
+    for ch in text.chars() {
+        if ch.is_ascii_whitespace() {
+            continue;
+        }
+        let digit = ch.to_digit(16)? as u8;
+        match high.take() {
+            Some(h) => out.push(h << 4 | digit),
+            None => high = Some(digit),
+        }
+    }
+    match high {
+        Some(_) => None,
+        None => Some(out),
+    }
+}
+
+/// Decodes a string into a byte buffer, as hex or else as base64.
+pub fn decode_bytes(text: &str, format: BytesFormat) -> Result<Vec<u8>> {
+    let (bytes, expected) = match format {
+        BytesFormat::Hex => (decode_hex(text), "hex encoded bytes"),
+        _ => (decode_base64(text), "base64 encoded bytes"),
+    };
+    bytes.ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(text), &expected))
+}
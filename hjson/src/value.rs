@@ -48,6 +48,8 @@ use num_traits::NumCast;
 use serde::{de, ser};
 
 use super::error::{Error, ErrorCode};
+use super::ser::BytesFormat;
+use super::util::{decode_bytes, encode_base64, encode_hex, parse_index, MapKeyDeserializer};
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -79,6 +81,23 @@ fn map_with_capacity<K: std::hash::Hash + Eq, V>(size: Option<usize>) -> Map<K,
 }
 
 /// Represents a Hjson/JSON value
+///
+/// A value written as Hjson or JSON, e.g. with `to_string`, is read back as an equal value,
+/// except for numbers that are read back as another variant:
+///
+/// - a non-negative `I64` is read back as `U64`,
+/// - an `F64` with an integral value below `1e21` is written without a fraction like in
+///   JavaScript, e.g. `1.0` as `1`, and read back as the `U64` or `I64` with the written digits
+///   if it fits in one, unless `FormatOptions::float_point` is set,
+/// - an `F64` that is not finite is written as `null`.
+///
+/// ```rust
+/// use serde_hjson::Value;
+///
+/// let text = serde_hjson::to_string(&Value::Array(vec![Value::I64(1), Value::F64(2.0)])).unwrap();
+/// let value: Value = serde_hjson::from_str(&text).unwrap();
+/// assert_eq!(value, Value::Array(vec![Value::U64(1), Value::U64(2)]));
+/// ```
 #[derive(Clone, PartialEq)]
 pub enum Value {
     /// Represents a JSON null value
@@ -144,12 +163,6 @@ impl Value {
     ///
     /// For more information read [RFC6901](https://tools.ietf.org/html/rfc6901).
     pub fn pointer<'a>(&'a self, pointer: &str) -> Option<&'a Value> {
-        fn parse_index(s: &str) -> Option<usize> {
-            if s.starts_with('+') || (s.starts_with('0') && s.len() != 1) {
-                return None;
-            }
-            s.parse().ok()
-        }
         if pointer.is_empty() {
             return Some(self);
         }
@@ -542,6 +555,229 @@ impl ser::Serializer for Serializer {
     type SerializeStruct = SerializeMap;
     type SerializeStructVariant = SerializeStructVariant;
 
+    #[inline]
+    fn serialize_bool(self, value: bool) -> Result<Value> {
+        BytesSerializer::default().serialize_bool(value)
+    }
+
+    #[inline]
+    fn serialize_i8(self, value: i8) -> Result<Value> {
+        BytesSerializer::default().serialize_i8(value)
+    }
+
+    #[inline]
+    fn serialize_i16(self, value: i16) -> Result<Value> {
+        BytesSerializer::default().serialize_i16(value)
+    }
+
+    #[inline]
+    fn serialize_i32(self, value: i32) -> Result<Value> {
+        BytesSerializer::default().serialize_i32(value)
+    }
+
+    #[inline]
+    fn serialize_i64(self, value: i64) -> Result<Value> {
+        BytesSerializer::default().serialize_i64(value)
+    }
+
+    #[inline]
+    fn serialize_u8(self, value: u8) -> Result<Value> {
+        BytesSerializer::default().serialize_u8(value)
+    }
+
+    #[inline]
+    fn serialize_u16(self, value: u16) -> Result<Value> {
+        BytesSerializer::default().serialize_u16(value)
+    }
+
+    #[inline]
+    fn serialize_u32(self, value: u32) -> Result<Value> {
+        BytesSerializer::default().serialize_u32(value)
+    }
+
+    #[inline]
+    fn serialize_u64(self, value: u64) -> Result<Value> {
+        BytesSerializer::default().serialize_u64(value)
+    }
+
+    #[inline]
+    fn serialize_f32(self, value: f32) -> Result<Value> {
+        BytesSerializer::default().serialize_f32(value)
+    }
+
+    #[inline]
+    fn serialize_f64(self, value: f64) -> Result<Value> {
+        BytesSerializer::default().serialize_f64(value)
+    }
+
+    #[inline]
+    fn serialize_char(self, value: char) -> Result<Value> {
+        BytesSerializer::default().serialize_char(value)
+    }
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<Value> {
+        BytesSerializer::default().serialize_str(value)
+    }
+
+    #[inline]
+    fn serialize_bytes(self, value: &[u8]) -> Result<Value> {
+        BytesSerializer::default().serialize_bytes(value)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<Value> {
+        BytesSerializer::default().serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Value> {
+        BytesSerializer::default().serialize_unit_struct(name)
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        BytesSerializer::default().serialize_unit_variant(name, variant_index, variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Value>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        BytesSerializer::default().serialize_newtype_struct(name, value)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        BytesSerializer::default().serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<Value> {
+        BytesSerializer::default().serialize_none()
+    }
+
+    #[inline]
+    fn serialize_some<V>(self, value: &V) -> Result<Value>
+    where
+        V: ?Sized + ser::Serialize,
+    {
+        BytesSerializer::default().serialize_some(value)
+    }
+
+    #[inline]
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        BytesSerializer::default().serialize_seq(len)
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        BytesSerializer::default().serialize_tuple(len)
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        BytesSerializer::default().serialize_tuple_struct(name, len)
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        BytesSerializer::default().serialize_tuple_variant(name, variant_index, variant, len)
+    }
+
+    #[inline]
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        BytesSerializer::default().serialize_map(len)
+    }
+
+    #[inline]
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        BytesSerializer::default().serialize_struct(name, len)
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        BytesSerializer::default().serialize_struct_variant(name, variant_index, variant, len)
+    }
+}
+
+/// A `serde::Serializer` like `Serializer` that writes byte buffers in the given format.
+/// Encoded byte buffers become strings.
+///
+/// ```rust
+/// use serde_hjson::value::BytesSerializer;
+/// use serde_hjson::{BytesFormat, Value};
+///
+/// struct Key(&'static [u8]);
+///
+/// impl serde::Serialize for Key {
+///     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+///         serializer.serialize_bytes(self.0)
+///     }
+/// }
+///
+/// let value = serde::Serialize::serialize(&Key(b"hi"), BytesSerializer::new(BytesFormat::Hex));
+/// assert_eq!(value.unwrap(), Value::String("6869".to_owned()));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BytesSerializer {
+    bytes: BytesFormat,
+}
+
+impl BytesSerializer {
+    /// Creates a serializer that writes byte buffers in the given format.
+    pub fn new(bytes: BytesFormat) -> Self {
+        BytesSerializer { bytes }
+    }
+}
+
+impl ser::Serializer for BytesSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeStructVariant;
+
     #[inline]
     fn serialize_bool(self, value: bool) -> Result<Value> {
         Ok(Value::Bool(value))
@@ -614,6 +850,11 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Value> {
+        match self.bytes {
+            BytesFormat::Array => {}
+            BytesFormat::Base64 => return Ok(Value::String(encode_base64(value))),
+            BytesFormat::Hex => return Ok(Value::String(encode_hex(value))),
+        }
         let mut state = self.serialize_seq(Some(value.len()))?;
         for byte in value {
             ser::SerializeSeq::serialize_element(&mut state, byte)?;
@@ -660,7 +901,7 @@ impl ser::Serializer for Serializer {
         T: ?Sized + ser::Serialize,
     {
         let mut values = Map::new();
-        values.insert(String::from(variant), to_value(&value)?);
+        values.insert(String::from(variant), value.serialize(self)?);
         Ok(Value::Object(values))
     }
 
@@ -680,6 +921,7 @@ impl ser::Serializer for Serializer {
     #[inline]
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         Ok(SerializeVec {
+            ser: self,
             vec: Vec::with_capacity(len.unwrap_or(0)),
         })
     }
@@ -707,6 +949,7 @@ impl ser::Serializer for Serializer {
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
         Ok(SerializeTupleVariant {
+            ser: self,
             name: variant,
             vec: Vec::with_capacity(len),
         })
@@ -715,6 +958,7 @@ impl ser::Serializer for Serializer {
     #[inline]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
         Ok(SerializeMap {
+            ser: self,
             map: map_with_capacity(len),
             next_key: None,
         })
@@ -738,6 +982,7 @@ impl ser::Serializer for Serializer {
         len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
         Ok(SerializeStructVariant {
+            ser: self,
             name: variant,
             map: map_with_capacity(Some(len)),
         })
@@ -746,23 +991,27 @@ impl ser::Serializer for Serializer {
 
 #[doc(hidden)]
 pub struct SerializeVec {
+    ser: BytesSerializer,
     vec: Vec<Value>,
 }
 
 #[doc(hidden)]
 pub struct SerializeTupleVariant {
+    ser: BytesSerializer,
     name: &'static str,
     vec: Vec<Value>,
 }
 
 #[doc(hidden)]
 pub struct SerializeMap {
+    ser: BytesSerializer,
     map: Map<String, Value>,
     next_key: Option<String>,
 }
 
 #[doc(hidden)]
 pub struct SerializeStructVariant {
+    ser: BytesSerializer,
     name: &'static str,
     map: Map<String, Value>,
 }
@@ -775,7 +1024,7 @@ impl ser::SerializeSeq for SerializeVec {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.vec.push(to_value(&value)?);
+        self.vec.push(value.serialize(self.ser)?);
         Ok(())
     }
 
@@ -824,7 +1073,7 @@ impl ser::SerializeTupleVariant for SerializeTupleVariant {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.vec.push(to_value(&value)?);
+        self.vec.push(value.serialize(self.ser)?);
         Ok(())
     }
 
@@ -845,8 +1094,11 @@ impl ser::SerializeMap for SerializeMap {
     where
         T: ?Sized + ser::Serialize,
     {
-        match to_value(key)? {
+        match key.serialize(self.ser)? {
             Value::String(s) => self.next_key = Some(s),
+            Value::I64(v) => self.next_key = Some(v.to_string()),
+            Value::U64(v) => self.next_key = Some(v.to_string()),
+            Value::Bool(v) => self.next_key = Some(v.to_string()),
             _ => return Err(Error::Syntax(ErrorCode::KeyMustBeAString, 0, 0)),
         };
         Ok(())
@@ -860,7 +1112,7 @@ impl ser::SerializeMap for SerializeMap {
         // Panic because this indicates a bug in the program rather than an
         // expected failure.
         let key = key.expect("serialize_value called before serialize_key");
-        self.map.insert(key, to_value(value)?);
+        self.map.insert(key, value.serialize(self.ser)?);
         Ok(())
     }
 
@@ -893,7 +1145,7 @@ impl ser::SerializeStructVariant for SerializeStructVariant {
     where
         T: ?Sized + ser::Serialize,
     {
-        self.map.insert(key.to_owned(), to_value(&value)?);
+        self.map.insert(key.to_owned(), value.serialize(self.ser)?);
         Ok(())
     }
 
@@ -914,20 +1166,83 @@ impl<'de> de::Deserializer<'de> for Value {
     where
         V: de::Visitor<'de>,
     {
-        match self {
+        BytesDeserializer::new(self, BytesFormat::Array).deserialize_any(visitor)
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        BytesDeserializer::new(self, BytesFormat::Array).deserialize_option(visitor)
+    }
+
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        BytesDeserializer::new(self, BytesFormat::Array).deserialize_enum(name, variants, visitor)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        BytesDeserializer::new(self, BytesFormat::Array).deserialize_newtype_struct(name, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// A `serde::Deserializer` that reads a `Value` like deserializing it directly, except that
+/// strings are decoded into byte buffers, like `serde_bytes::ByteBuf`, in the given format.
+/// Arrays of numbers are always accepted, and with `BytesFormat::Array` strings give their
+/// UTF-8 bytes like without it.
+pub struct BytesDeserializer {
+    value: Value,
+    bytes: BytesFormat,
+}
+
+impl BytesDeserializer {
+    /// Creates a deserializer that decodes the strings of byte buffers in the given format.
+    pub fn new(value: Value, bytes: BytesFormat) -> Self {
+        BytesDeserializer { value, bytes }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for BytesDeserializer {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let bytes = self.bytes;
+        match self.value {
             Value::Null => visitor.visit_unit(),
             Value::Bool(v) => visitor.visit_bool(v),
             Value::I64(v) => visitor.visit_i64(v),
             Value::U64(v) => visitor.visit_u64(v),
             Value::F64(v) => visitor.visit_f64(v),
             Value::String(v) => visitor.visit_string(v),
-            Value::Array(v) => visitor.visit_seq(SeqDeserializer {
-                iter: v.into_iter(),
-            }),
-            Value::Object(v) => visitor.visit_map(MapDeserializer {
-                iter: v.into_iter(),
-                value: None,
-            }),
+            Value::Array(v) => visitor.visit_seq(SeqDeserializer::new(v, bytes)),
+            Value::Object(v) => visitor.visit_map(MapDeserializer::new(v, bytes)),
         }
     }
 
@@ -936,7 +1251,7 @@ impl<'de> de::Deserializer<'de> for Value {
     where
         V: de::Visitor<'de>,
     {
-        match self {
+        match self.value {
             Value::Null => visitor.visit_none(),
             _ => visitor.visit_some(self),
         }
@@ -952,7 +1267,7 @@ impl<'de> de::Deserializer<'de> for Value {
     where
         V: de::Visitor<'de>,
     {
-        let (variant, value) = match self {
+        let (variant, value) = match self.value {
             Value::Object(value) => {
                 let mut iter = value.into_iter();
                 let Some((variant, value)) = iter.next() else {
@@ -979,7 +1294,11 @@ impl<'de> de::Deserializer<'de> for Value {
             }
         };
 
-        visitor.visit_enum(EnumDeserializer { value, variant })
+        visitor.visit_enum(EnumDeserializer {
+            value,
+            variant,
+            bytes: self.bytes,
+        })
     }
 
     #[inline]
@@ -994,16 +1313,38 @@ impl<'de> de::Deserializer<'de> for Value {
         visitor.visit_newtype_struct(self)
     }
 
+    /// Decodes a string in the format of the deserializer, and reads any other value like
+    /// `deserialize_any`.
+    #[inline]
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::String(ref s) if self.bytes != BytesFormat::Array => {
+                visitor.visit_byte_buf(decode_bytes(s, self.bytes)?)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
     serde::forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf unit unit_struct seq tuple
-        tuple_struct map struct identifier ignored_any
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
     }
 }
 
 struct EnumDeserializer {
     variant: String,
     value: Option<Value>,
+    bytes: BytesFormat,
 }
 
 impl<'de> de::EnumAccess<'de> for EnumDeserializer {
@@ -1015,14 +1356,23 @@ impl<'de> de::EnumAccess<'de> for EnumDeserializer {
     where
         V: de::DeserializeSeed<'de>,
     {
-        let variant = de::IntoDeserializer::into_deserializer(self.variant);
-        let visitor = VariantDeserializer { val: self.value };
-        seed.deserialize(variant).map(|v| (v, visitor))
+        let variant: de::value::StrDeserializer<Error> =
+            de::IntoDeserializer::into_deserializer(self.variant.as_str());
+        let value = seed.deserialize(variant)?;
+        let visitor = VariantDeserializer {
+            val: self.value,
+            variant: self.variant,
+            bytes: self.bytes,
+        };
+        Ok((value, visitor))
     }
 }
 
 struct VariantDeserializer {
     val: Option<Value>,
+    // the key of the value, for the path of its errors
+    variant: String,
+    bytes: BytesFormat,
 }
 
 impl<'de> de::VariantAccess<'de> for VariantDeserializer {
@@ -1030,7 +1380,8 @@ impl<'de> de::VariantAccess<'de> for VariantDeserializer {
 
     fn unit_variant(self) -> Result<()> {
         match self.val {
-            Some(val) => de::Deserialize::deserialize(val),
+            Some(val) => de::Deserialize::deserialize(BytesDeserializer::new(val, self.bytes))
+                .map_err(|err| err.in_member(&self.variant)),
             None => Ok(()),
         }
     }
@@ -1040,7 +1391,9 @@ impl<'de> de::VariantAccess<'de> for VariantDeserializer {
         T: de::DeserializeSeed<'de>,
     {
         match self.val {
-            Some(value) => seed.deserialize(value),
+            Some(value) => seed
+                .deserialize(BytesDeserializer::new(value, self.bytes))
+                .map_err(|err| err.in_member(&self.variant)),
             None => Err(serde::de::Error::invalid_type(
                 de::Unexpected::UnitVariant,
                 &"newtype variant",
@@ -1053,38 +1406,51 @@ impl<'de> de::VariantAccess<'de> for VariantDeserializer {
         V: de::Visitor<'de>,
     {
         let val = self.val.expect("val is missing");
-        if let Value::Array(fields) = val {
-            visitor.visit_seq(SeqDeserializer {
-                iter: fields.into_iter(),
-            })
+        let result = if let Value::Array(fields) = val {
+            visitor.visit_seq(SeqDeserializer::new(fields, self.bytes))
         } else {
             Err(de::Error::invalid_type(val.as_unexpected(), &visitor))
-        }
+        };
+        result.map_err(|err| err.in_member(&self.variant))
     }
 
     fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        match self.val {
-            Some(Value::Object(fields)) => visitor.visit_map(MapDeserializer {
-                iter: fields.into_iter(),
-                value: None,
-            }),
+        let result = match self.val {
+            Some(Value::Object(fields)) => {
+                visitor.visit_map(MapDeserializer::new(fields, self.bytes))
+            }
             Some(other) => Err(de::Error::invalid_type(
                 other.as_unexpected(),
                 &"struct variant",
             )),
-            None => Err(de::Error::invalid_type(
-                de::Unexpected::UnitVariant,
-                &"struct variant",
-            )),
-        }
+            None => {
+                return Err(de::Error::invalid_type(
+                    de::Unexpected::UnitVariant,
+                    &"struct variant",
+                ))
+            }
+        };
+        result.map_err(|err| err.in_member(&self.variant))
     }
 }
 
 struct SeqDeserializer {
     iter: vec::IntoIter<Value>,
+    index: usize,
+    bytes: BytesFormat,
+}
+
+impl SeqDeserializer {
+    fn new(values: Vec<Value>, bytes: BytesFormat) -> Self {
+        SeqDeserializer {
+            iter: values.into_iter(),
+            index: 0,
+            bytes,
+        }
+    }
 }
 
 impl<'de> de::SeqAccess<'de> for SeqDeserializer {
@@ -1094,10 +1460,14 @@ impl<'de> de::SeqAccess<'de> for SeqDeserializer {
     where
         T: de::DeserializeSeed<'de>,
     {
-        match self.iter.next() {
-            Some(value) => Ok(Some(seed.deserialize(value)?)),
-            None => Ok(None),
-        }
+        let Some(value) = self.iter.next() else {
+            return Ok(None);
+        };
+        let value = seed
+            .deserialize(BytesDeserializer::new(value, self.bytes))
+            .map_err(|err| err.in_member(&self.index.to_string()))?;
+        self.index += 1;
+        Ok(Some(value))
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -1110,7 +1480,20 @@ impl<'de> de::SeqAccess<'de> for SeqDeserializer {
 
 struct MapDeserializer {
     iter: MapIntoIter<String, Value>,
+    key: String,
     value: Option<Value>,
+    bytes: BytesFormat,
+}
+
+impl MapDeserializer {
+    fn new(map: Map<String, Value>, bytes: BytesFormat) -> Self {
+        MapDeserializer {
+            iter: map.into_iter(),
+            key: String::new(),
+            value: None,
+            bytes,
+        }
+    }
 }
 
 impl<'de> de::MapAccess<'de> for MapDeserializer {
@@ -1122,8 +1505,12 @@ impl<'de> de::MapAccess<'de> for MapDeserializer {
     {
         match self.iter.next() {
             Some((key, value)) => {
+                self.key = key;
                 self.value = Some(value);
-                Ok(Some(seed.deserialize(Value::String(key))?))
+                let key = seed
+                    .deserialize(MapKeyDeserializer::new(&self.key))
+                    .map_err(|err| err.in_member(&self.key))?;
+                Ok(Some(key))
             }
             None => Ok(None),
         }
@@ -1134,7 +1521,8 @@ impl<'de> de::MapAccess<'de> for MapDeserializer {
         V: de::DeserializeSeed<'de>,
     {
         let value = self.value.take().expect("value is missing");
-        seed.deserialize(value)
+        seed.deserialize(BytesDeserializer::new(value, self.bytes))
+            .map_err(|err| err.in_member(&self.key))
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -2,14 +2,16 @@ extern crate clap;
 extern crate core;
 extern crate serde;
 extern crate serde_hjson;
-extern crate serde_json;
 
 use clap::Parser;
-use serde_hjson::Value;
+use serde::Serialize;
+use serde_hjson::ser::Formatter;
+use serde_hjson::{ColorFormatter, HjsonFormatter, JsonFormatter, Serializer, Value};
 
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
+use std::io::IsTerminal;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -28,11 +30,39 @@ pub struct HJson {
     /// If specified, read from this file, otherwise read from stdin
     input: Option<std::path::PathBuf>,
 
+    /// Highlight the output with colors
+    #[arg(long, value_enum, default_value_t = Color::Auto)]
+    color: Color,
+
     /// Show version
     #[arg(long, short = 'V', action)]
     version: bool,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Color {
+    /// When stdout is a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+fn print<F>(data: &Value, formatter: F, color: bool)
+where
+    F: Formatter,
+{
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if color {
+        let mut ser = Serializer::with_formatter(&mut out, ColorFormatter::new(formatter));
+        data.serialize(&mut ser).unwrap();
+    } else {
+        data.serialize(&mut Serializer::with_formatter(&mut out, formatter))
+            .unwrap();
+    }
+    writeln!(out).unwrap();
+}
+
 fn main() {
     let args = HJson::parse();
     if args.version {
@@ -50,11 +80,17 @@ fn main() {
 
     let data: Value = serde_hjson::from_str(&buffer).unwrap();
 
+    let color = match args.color {
+        Color::Auto => io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        Color::Always => true,
+        Color::Never => false,
+    };
+
     if args.as_formatted_json {
-        println!("{}", serde_json::to_string_pretty(&data).unwrap());
+        print(&data, JsonFormatter::pretty(), color);
     } else if args.as_json {
-        println!("{}", serde_json::to_string(&data).unwrap());
+        print(&data, JsonFormatter::compact(), color);
     } else {
-        println!("{}", serde_hjson::to_string(&data).unwrap());
+        print(&data, HjsonFormatter::new(), color);
     }
 }
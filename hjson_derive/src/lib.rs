@@ -0,0 +1,385 @@
+//! `#[derive(HjsonDoc)]` for serde-hjson.
+//!
+//! The derive implements `serde_hjson::doc::HjsonDoc` from the `///` comments of a struct or
+//! enum, its fields and its variants. Use it through the `derive` feature of `serde-hjson`.
+
+#![deny(missing_docs)]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::ParseStream;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Expr, ExprLit, Field, Fields, Lit, LitStr,
+    Meta, Token, Type,
+};
+
+/// Derives `serde_hjson::doc::HjsonDoc` from the `///` comments of a type.
+#[proc_macro_derive(HjsonDoc)]
+pub fn derive_hjson_doc(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).into()
+}
+
+fn expand(input: &DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attrs = SerdeAttrs::parse(&input.attrs);
+    let comment = option(doc_comment(&input.attrs));
+
+    let body = match input.data {
+        Data::Struct(ref data) if attrs.transparent => match serialized(&data.fields).next() {
+            Some((_, field)) => transparent_doc(&field.ty, &comment),
+            None => quote!(::serde_hjson::doc::Doc::new(#comment)),
+        },
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(_) => {
+                let members = fields_doc(&data.fields, attrs.rename_all);
+                quote! {
+                    #[allow(unused_mut)]
+                    let mut doc = ::serde_hjson::doc::Doc::new(#comment);
+                    #members
+                    doc
+                }
+            }
+            Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                transparent_doc(&fields.unnamed[0].ty, &comment)
+            }
+            _ => quote!(::serde_hjson::doc::Doc::new(#comment)),
+        },
+        Data::Enum(ref data) => {
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                let variant_attrs = SerdeAttrs::parse(&variant.attrs);
+                if variant_attrs.skip {
+                    continue;
+                }
+                let name = variant_attrs.rename.unwrap_or_else(|| {
+                    attrs
+                        .rename_all
+                        .apply_to_variant(&unraw(&variant.ident.to_string()))
+                });
+                let variant_comment = option(doc_comment(&variant.attrs));
+                let value_doc = match variant.fields {
+                    Fields::Named(_) => {
+                        let rule = variant_attrs.rename_all.or(attrs.rename_all_fields);
+                        let members = fields_doc(&variant.fields, rule);
+                        quote! {{
+                            #[allow(unused_mut)]
+                            let mut doc = ::serde_hjson::doc::Doc::new(None);
+                            #members
+                            Some(doc)
+                        }}
+                    }
+                    Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                        probe(&fields.unnamed[0].ty)
+                    }
+                    _ => quote!(None),
+                };
+                variants.push(quote! {
+                    doc.members.push(::serde_hjson::doc::Member {
+                        name: #name,
+                        comment: #variant_comment,
+                        doc: || #value_doc,
+                    });
+                });
+            }
+            quote! {
+                let mut doc = ::serde_hjson::doc::Doc::new(#comment);
+                doc.variants = true;
+                #(#variants)*
+                doc
+            }
+        }
+        Data::Union(_) => {
+            return syn::Error::new_spanned(name, "HjsonDoc can not be derived for unions")
+                .to_compile_error();
+        }
+    };
+
+    quote! {
+        impl #impl_generics ::serde_hjson::doc::HjsonDoc for #name #ty_generics #where_clause {
+            fn hjson_doc() -> ::serde_hjson::doc::Doc {
+                #[allow(unused_imports)]
+                use ::serde_hjson::doc::{Documented as _, Undocumented as _};
+                #body
+            }
+        }
+    }
+}
+
+// The documentation of a type that is written like the type of its only field.
+fn transparent_doc(ty: &Type, comment: &TokenStream2) -> TokenStream2 {
+    let inner = probe(ty);
+    quote! {
+        let mut doc = #inner.unwrap_or_default();
+        doc.comment = #comment;
+        doc
+    }
+}
+
+// Pushes the documentation of named fields to `doc`.
+fn fields_doc(fields: &Fields, rename_all: RenameRule) -> TokenStream2 {
+    let mut members = Vec::new();
+    for (attrs, field) in serialized(fields) {
+        let ty = &field.ty;
+        let value_doc = probe(ty);
+        if attrs.flatten {
+            members.push(quote! {
+                if let Some(flattened) = #value_doc {
+                    doc.members.extend(flattened.members);
+                }
+            });
+            continue;
+        }
+        let ident = field.ident.as_ref().map(|ident| unraw(&ident.to_string()));
+        let name = attrs
+            .rename
+            .unwrap_or_else(|| rename_all.apply_to_field(&ident.unwrap_or_default()));
+        let comment = option(doc_comment(&field.attrs));
+        members.push(quote! {
+            doc.members.push(::serde_hjson::doc::Member {
+                name: #name,
+                comment: #comment,
+                doc: || #value_doc,
+            });
+        });
+    }
+    quote!(#(#members)*)
+}
+
+// The fields that are serialized, with their serde attributes.
+fn serialized(fields: &Fields) -> impl Iterator<Item = (SerdeAttrs, &Field)> {
+    fields
+        .iter()
+        .map(|field| (SerdeAttrs::parse(&field.attrs), field))
+        .filter(|(attrs, _)| !attrs.skip)
+}
+
+// The documentation of a type, if it implements `HjsonDoc`.
+fn probe(ty: &Type) -> TokenStream2 {
+    quote! {
+        (&&::serde_hjson::doc::DocProbe::<#ty>(::core::marker::PhantomData)).doc_of()
+    }
+}
+
+fn option(value: Option<String>) -> TokenStream2 {
+    match value {
+        Some(value) => quote!(Some(#value)),
+        None => quote!(None),
+    }
+}
+
+// Joins the lines of the doc comments, without the space that follows `///`.
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        if !attr.path().is_ident("doc") {
+            continue;
+        }
+        if let Meta::NameValue(ref meta) = attr.meta {
+            if let Expr::Lit(ExprLit {
+                lit: Lit::Str(ref text),
+                ..
+            }) = meta.value
+            {
+                for line in text.value().split('\n') {
+                    let line = line.strip_prefix(' ').unwrap_or(line);
+                    lines.push(line.trim_end().to_owned());
+                }
+            }
+        }
+    }
+    while lines.last().map_or(false, |line| line.is_empty()) {
+        lines.pop();
+    }
+    let start = lines.iter().take_while(|line| line.is_empty()).count();
+    if start == lines.len() {
+        None
+    } else {
+        Some(lines[start..].join("\n"))
+    }
+}
+
+fn unraw(ident: &str) -> String {
+    ident.trim_start_matches("r#").to_owned()
+}
+
+// The serde attributes that change the serialized names and members.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: RenameRule,
+    rename_all_fields: RenameRule,
+    skip: bool,
+    flatten: bool,
+    transparent: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> Self {
+        let mut result = SerdeAttrs::default();
+        for attr in attrs {
+            if !attr.path().is_ident("serde") {
+                continue;
+            }
+            // serde reports invalid attributes itself
+            let _ = attr.parse_nested_meta(|meta| {
+                let path = &meta.path;
+                if path.is_ident("rename") {
+                    result.rename = serialize_name(&meta)?.map(|name| name.value());
+                } else if path.is_ident("rename_all") {
+                    result.rename_all = RenameRule::parse(serialize_name(&meta)?);
+                } else if path.is_ident("rename_all_fields") {
+                    result.rename_all_fields = RenameRule::parse(serialize_name(&meta)?);
+                } else if path.is_ident("skip") || path.is_ident("skip_serializing") {
+                    result.skip = true;
+                } else if path.is_ident("flatten") {
+                    result.flatten = true;
+                } else if path.is_ident("transparent") {
+                    result.transparent = true;
+                } else {
+                    skip_meta(meta.input)?;
+                }
+                Ok(())
+            });
+        }
+        result
+    }
+}
+
+// Reads `name = "..."` or the serialize name of `name(serialize = "...")`.
+fn serialize_name(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Option<LitStr>> {
+    if meta.input.peek(Token![=]) {
+        return meta.value()?.parse().map(Some);
+    }
+    let mut name = None;
+    meta.parse_nested_meta(|nested| {
+        if nested.path.is_ident("serialize") {
+            name = Some(nested.value()?.parse()?);
+        } else {
+            skip_meta(nested.input)?;
+        }
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+// Skips the value of an attribute that is not needed.
+fn skip_meta(input: ParseStream) -> syn::Result<()> {
+    if input.peek(Token![=]) {
+        input.parse::<Token![=]>()?;
+        input.parse::<Expr>()?;
+    } else if input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in input);
+        content.parse::<TokenStream2>()?;
+    }
+    Ok(())
+}
+
+// The case conversions of `rename_all`, like serde applies them.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum RenameRule {
+    #[default]
+    None,
+    LowerCase,
+    UpperCase,
+    PascalCase,
+    CamelCase,
+    SnakeCase,
+    ScreamingSnakeCase,
+    KebabCase,
+    ScreamingKebabCase,
+}
+
+impl RenameRule {
+    fn parse(name: Option<LitStr>) -> Self {
+        match name.map(|name| name.value()).as_deref() {
+            Some("lowercase") => RenameRule::LowerCase,
+            Some("UPPERCASE") => RenameRule::UpperCase,
+            Some("PascalCase") => RenameRule::PascalCase,
+            Some("camelCase") => RenameRule::CamelCase,
+            Some("snake_case") => RenameRule::SnakeCase,
+            Some("SCREAMING_SNAKE_CASE") => RenameRule::ScreamingSnakeCase,
+            Some("kebab-case") => RenameRule::KebabCase,
+            Some("SCREAMING-KEBAB-CASE") => RenameRule::ScreamingKebabCase,
+            _ => RenameRule::None,
+        }
+    }
+
+    fn or(self, other: RenameRule) -> Self {
+        if self == RenameRule::None {
+            other
+        } else {
+            self
+        }
+    }
+
+    // Renames a variant, which is written in PascalCase.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::None | RenameRule::PascalCase => variant.to_owned(),
+            RenameRule::LowerCase => variant.to_ascii_lowercase(),
+            RenameRule::UpperCase => variant.to_ascii_uppercase(),
+            RenameRule::CamelCase => {
+                let mut chars = variant.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            RenameRule::SnakeCase => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            RenameRule::ScreamingSnakeCase => RenameRule::SnakeCase
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            RenameRule::KebabCase => RenameRule::SnakeCase
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+            RenameRule::ScreamingKebabCase => RenameRule::ScreamingSnakeCase
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+
+    // Renames a field, which is written in snake_case.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::None | RenameRule::LowerCase | RenameRule::SnakeCase => field.to_owned(),
+            RenameRule::UpperCase | RenameRule::ScreamingSnakeCase => field.to_ascii_uppercase(),
+            RenameRule::PascalCase => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::CamelCase => RenameRule::CamelCase
+                .apply_to_variant(&RenameRule::PascalCase.apply_to_field(field)),
+            RenameRule::KebabCase => field.replace('_', "-"),
+            RenameRule::ScreamingKebabCase => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
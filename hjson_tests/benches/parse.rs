@@ -0,0 +1,82 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::fs;
+use std::path::Path;
+
+// Reads the test assets whose name ends with the given suffix, skipping the
+// inputs that are expected to fail.
+fn assets(suffix: &str) -> Vec<String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let mut names: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            name.ends_with(suffix) && !name.starts_with("fail")
+        })
+        .collect();
+    names.sort();
+    names
+        .iter()
+        .map(|path| fs::read_to_string(path).unwrap())
+        .collect()
+}
+
+fn parse_assets(c: &mut Criterion) {
+    let json = assets("_result.json");
+    let hjson = assets("_test.hjson");
+
+    let mut group = c.benchmark_group("assets");
+    group.throughput(Throughput::Bytes(json.iter().map(|s| s.len() as u64).sum()));
+    group.bench_function("serde_json", |b| {
+        b.iter(|| {
+            for text in &json {
+                black_box(serde_json::from_str::<serde_json::Value>(text).unwrap());
+            }
+        })
+    });
+    group.bench_function("serde_hjson/json", |b| {
+        b.iter(|| {
+            for text in &json {
+                black_box(serde_hjson::from_str::<serde_hjson::Value>(text).unwrap());
+            }
+        })
+    });
+    group.throughput(Throughput::Bytes(
+        hjson.iter().map(|s| s.len() as u64).sum(),
+    ));
+    group.bench_function("serde_hjson/hjson", |b| {
+        b.iter(|| {
+            for text in &hjson {
+                black_box(serde_hjson::from_str::<serde_hjson::Value>(text).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+// All results in one large document, where the per-call overhead does not
+// hide the throughput of the reader.
+fn parse_large(c: &mut Criterion) {
+    let items = assets("_result.json");
+    let mut text = String::from("[\n");
+    for _ in 0..20 {
+        for item in &items {
+            text.push_str(item);
+            text.push_str(",\n");
+        }
+    }
+    text.push_str("null\n]\n");
+
+    let mut group = c.benchmark_group("large");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("serde_json", |b| {
+        b.iter(|| black_box(serde_json::from_str::<serde_json::Value>(&text).unwrap()))
+    });
+    group.bench_function("serde_hjson", |b| {
+        b.iter(|| black_box(serde_hjson::from_str::<serde_hjson::Value>(&text).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_assets, parse_large);
+criterion_main!(benches);
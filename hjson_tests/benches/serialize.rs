@@ -0,0 +1,69 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::fs;
+use std::path::Path;
+
+// All test results in one large document, with a mix of quoteless, quoted
+// and multiline strings, numbers and nested containers.
+fn large_value() -> (serde_hjson::Value, serde_json::Value) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let mut names: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.to_str().unwrap().ends_with("_result.json"))
+        .collect();
+    names.sort();
+
+    let mut text = String::from("[\n");
+    for _ in 0..20 {
+        for path in &names {
+            text.push_str(&fs::read_to_string(path).unwrap());
+            text.push_str(",\n");
+        }
+    }
+    text.push_str("null\n]\n");
+    (
+        serde_hjson::from_str(&text).unwrap(),
+        serde_json::from_str(&text).unwrap(),
+    )
+}
+
+fn serialize_large(c: &mut Criterion) {
+    let (hjson, json) = large_value();
+
+    let mut group = c.benchmark_group("serialize");
+    let len = serde_hjson::to_string(&hjson).unwrap().len();
+    group.throughput(Throughput::Bytes(len as u64));
+    group.bench_function("serde_hjson", |b| {
+        b.iter(|| black_box(serde_hjson::to_vec(&hjson).unwrap()))
+    });
+    let len = serde_json::to_string_pretty(&json).unwrap().len();
+    group.throughput(Throughput::Bytes(len as u64));
+    group.bench_function("serde_json/pretty", |b| {
+        b.iter(|| black_box(serde_json::to_vec_pretty(&json).unwrap()))
+    });
+    group.finish();
+}
+
+// Strings only, so the time is spent deciding how to quote them.
+fn serialize_strings(c: &mut Criterion) {
+    let strings: Vec<String> = (0..1000)
+        .map(|i| match i % 5 {
+            0 => format!("plain text number {i}"),
+            1 => format!("{i}"),
+            2 => format!("needs \"quotes\" {i}"),
+            3 => format!("line one\nline two {i}"),
+            _ => format!("true, but not a keyword {i}"),
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("strings");
+    let len = serde_hjson::to_string(&strings).unwrap().len();
+    group.throughput(Throughput::Bytes(len as u64));
+    group.bench_function("serde_hjson", |b| {
+        b.iter(|| black_box(serde_hjson::to_vec(&strings).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, serialize_large, serialize_strings);
+criterion_main!(benches);
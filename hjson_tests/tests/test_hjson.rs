@@ -54,17 +54,26 @@ macro_rules! run_test {
                     name, rjson, actual_json
                 );
             }
-            $is_success &= (rhjson == actual_hjson && rjson == actual_json);
+            // the built-in JSON output needs no fixes
+            let builtin_json = serde_hjson::to_json_string_pretty(&udata).unwrap() + "\n";
+            if rjson != builtin_json {
+                println!(
+                    "{:?}\n---json expected\n{}\n---built-in json actual\n{}\n---\n",
+                    name, rjson, builtin_json
+                );
+            }
+            $is_success &=
+                (rhjson == actual_hjson && rjson == actual_json && rjson == builtin_json);
         }
     }};
 }
 
 // add fixes where rust's json differs from javascript
 
-fn std_fix(json: &str) -> Cow<str> {
+fn std_fix(json: &str) -> Cow<'_, str> {
     // serde_json serializes integers with a superfluous .0 suffix
     let re = Regex::new(r"(?m)(?P<d>\d)\.0(?P<s>,?)$").unwrap();
-    re.replace_all(&json, "$d$s")
+    re.replace_all(json, "$d$s")
 }
 
 fn fix_kan(json: &str) -> String {
@@ -82,7 +91,7 @@ fn test_hjson() {
     let mut done: Vec<String> = Vec::new();
     let mut is_success: bool = true;
 
-    println!("");
+    println!();
     run_test!(charset, done, std_fix, is_success);
     run_test!(comments, done, std_fix, is_success);
     run_test!(empty, done, std_fix, is_success);
@@ -172,14 +181,14 @@ fn test_hjson() {
 
     let missing = all
         .into_iter()
-        .filter(|x| done.iter().find(|y| &x == y) == None)
+        .filter(|x| !done.iter().any(|y| x == y))
         .collect::<Vec<String>>();
 
-    if missing.len() > 0 {
+    if !missing.is_empty() {
         for item in missing {
             println!("missing: {}", item);
         }
-        assert!(false);
+        panic!("missing tests");
     }
 }
 
@@ -224,3 +233,1767 @@ pub fn integer_type() {
     let json: Value = serde_hjson::from_str("123").unwrap();
     assert!(json.is_number())
 }
+
+#[test]
+pub fn reader_events() {
+    use serde_hjson::reader::{Event, Number, Reader, SliceInput};
+
+    let text = "{\n  # port\n  port: 80\n  hosts: [1, \"b\"]\n}";
+    let mut rdr = Reader::new(text.bytes());
+    let mut events = Vec::new();
+    while let Some((event, span)) = rdr.next_spanned().unwrap() {
+        events.push((format!("{:?}", event), &text[span.start..span.end]));
+    }
+    rdr.end().unwrap();
+
+    assert_eq!(
+        events,
+        [
+            (format!("{:?}", Event::StartObject), "{"),
+            (format!("{:?}", Event::Comment("# port")), "# port"),
+            (format!("{:?}", Event::Key("port")), "port"),
+            (format!("{:?}", Event::Number(Number::U64(80))), "80"),
+            (format!("{:?}", Event::Key("hosts")), "hosts"),
+            (format!("{:?}", Event::StartArray), "["),
+            (format!("{:?}", Event::Number(Number::U64(1))), "1"),
+            (format!("{:?}", Event::String("b")), "\"b\""),
+            (format!("{:?}", Event::EndArray), "]"),
+            (format!("{:?}", Event::EndObject), "}"),
+        ]
+    );
+
+    // the same events from a slice, which is read in place
+    let mut rdr = Reader::new(SliceInput::new(text.as_bytes()));
+    let mut sliced = Vec::new();
+    while let Some((event, span)) = rdr.next_spanned().unwrap() {
+        sliced.push((format!("{:?}", event), &text[span.start..span.end]));
+    }
+    assert_eq!(sliced, events);
+
+    // comments that are not valid UTF-8 are reported with replacement characters
+    let mut rdr = Reader::new(SliceInput::new(b"# a\xff\n1"));
+    assert_eq!(
+        rdr.next_event().unwrap(),
+        Some(Event::Comment("# a\u{fffd}"))
+    );
+    assert_eq!(
+        rdr.next_event().unwrap(),
+        Some(Event::Number(Number::U64(1)))
+    );
+
+    // long runs of comments are skipped without recursion
+    for comment in ["#c\n", "//c\n", "/*c*/"] {
+        let text = comment.repeat(200_000) + "1";
+        assert_eq!(
+            serde_hjson::from_str::<Value>(&text).unwrap(),
+            Value::U64(1)
+        );
+        let mut rdr = Reader::new(SliceInput::new(text.as_bytes()));
+        let mut comments = 0;
+        while let Some(Event::Comment(_)) = rdr.next_event().unwrap() {
+            comments += 1;
+        }
+        assert_eq!(comments, 200_000);
+    }
+}
+
+#[test]
+pub fn reader_root() {
+    use serde_hjson::reader::{Event, Reader};
+
+    let mut rdr = Reader::new_for_root("a: 1\nb: true\n".bytes());
+    rdr.set_comments(false);
+    assert_eq!(rdr.next_event().unwrap(), Some(Event::StartObject));
+    assert_eq!(rdr.next_event().unwrap(), Some(Event::Key("a")));
+    rdr.next_event().unwrap();
+    assert_eq!(rdr.next_event().unwrap(), Some(Event::Key("b")));
+    assert_eq!(rdr.next_event().unwrap(), Some(Event::Bool(true)));
+    assert_eq!(rdr.depth(), 1);
+    assert_eq!(rdr.next_event().unwrap(), Some(Event::EndObject));
+    assert_eq!(rdr.next_event().unwrap(), None);
+}
+
+#[test]
+pub fn push_deserializer() {
+    use serde_hjson::de::{Progress, PushDeserializer};
+
+    let text = "{a: 1}\n# c\n[x\n2.5, '''\n  ml\n  '''\n]\n\"s\"\n42\nnull";
+    let expected: Vec<Value> = serde_hjson::StreamDeserializer::new(text.bytes())
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(expected.len(), 5);
+
+    for chunk in 1..text.len() {
+        let mut de = PushDeserializer::<Value>::new();
+        let mut values = Vec::new();
+        for part in text.as_bytes().chunks(chunk) {
+            de.feed(part);
+            while let Progress::Value(v) = de.next_value().unwrap() {
+                values.push(v);
+            }
+        }
+        de.finish();
+        while let Progress::Value(v) = de.next_value().unwrap() {
+            values.push(v);
+        }
+        assert_eq!(de.next_value().unwrap(), Progress::Done);
+        assert_eq!(values, expected);
+    }
+}
+
+#[test]
+pub fn push_deserializer_long_values() {
+    use serde_hjson::de::{Progress, PushDeserializer};
+
+    // a value that is cut off is not scanned again for every chunk, which
+    // would take quadratic time here
+    let long = "ab".repeat(500_000);
+    for text in [
+        format!("[\"{}\"]\n", long),
+        format!("['''{}''']\n", long),
+        format!("[{}\n]\n", long),
+        format!("{{a: {}\n}}\n", long),
+    ] {
+        let mut de = PushDeserializer::<Value>::new();
+        let mut values = Vec::new();
+        for part in text.as_bytes().chunks(16) {
+            de.feed(part);
+            while let Progress::Value(v) = de.next_value().unwrap() {
+                values.push(v);
+            }
+        }
+        assert_eq!(values, vec![serde_hjson::from_str::<Value>(&text).unwrap()]);
+        assert_eq!(de.offset(), text.len() - 1);
+    }
+}
+
+#[test]
+pub fn push_deserializer_error_position() {
+    use serde_hjson::de::{Progress, PushDeserializer};
+
+    let mut de = PushDeserializer::<Value>::new();
+    de.feed(b"{a: 1}\n{\n  b");
+    assert!(matches!(de.next_value().unwrap(), Progress::Value(_)));
+    de.feed(b" c: 2\n}");
+    match de.next_value() {
+        Err(serde_hjson::Error::Syntax(_, line, _)) => assert_eq!(line, 3),
+        _ => panic!("expected a syntax error"),
+    }
+}
+
+#[test]
+pub fn push_deserializer_skips_mismatched_value() {
+    use serde_hjson::de::{Progress, PushDeserializer};
+    use std::collections::BTreeMap;
+
+    let mut de = PushDeserializer::<BTreeMap<String, u16>>::new();
+    de.feed(b"{a: \"x\"}\n{a: 1}\n");
+    match de.next_value() {
+        Err(serde_hjson::Error::Data(_, path, line, _)) => {
+            assert_eq!(path, "/a");
+            assert_eq!(line, 1);
+        }
+        _ => panic!("expected a data error"),
+    }
+    assert_eq!(de.buffered(), 8);
+
+    let mut expected = BTreeMap::new();
+    expected.insert("a".to_owned(), 1);
+    assert_eq!(de.next_value().unwrap(), Progress::Value(expected));
+    assert_eq!(de.next_value().unwrap(), Progress::NeedMoreData);
+
+    // a syntax error ends the stream
+    de.feed(b"{a: 1}}\n{a: 2}\n");
+    assert!(matches!(de.next_value().unwrap(), Progress::Value(_)));
+    assert!(matches!(
+        de.next_value(),
+        Err(serde_hjson::Error::Syntax(..))
+    ));
+    assert!(matches!(
+        de.next_value(),
+        Err(serde_hjson::Error::Syntax(..))
+    ));
+}
+
+#[test]
+pub fn async_io() {
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+    use futures::StreamExt;
+
+    let value: Value =
+        block_on(serde_hjson::from_async_reader(Cursor::new(b"a: 1\nb: x\n"))).unwrap();
+    assert_eq!(value.find("b").and_then(Value::as_str), Some("x"));
+
+    let mut out = Cursor::new(Vec::new());
+    block_on(serde_hjson::to_async_writer(&mut out, &value)).unwrap();
+    assert_eq!(out.into_inner(), serde_hjson::to_vec(&value).unwrap());
+
+    let stream =
+        serde_hjson::AsyncStreamDeserializer::<_, Value>::new(Cursor::new(b"{a: 1}\n[2]\n3"));
+    let values: Vec<Value> = block_on(stream.map(|v| v.unwrap()).collect());
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[2].as_u64(), Some(3));
+}
+
+#[test]
+pub fn document_stream() {
+    use serde_hjson::de::{DocumentStream, Separator};
+
+    let input = "a: 1\nb: 2\n\n\n# only a comment\n\n{c: 3\n\nd: 4\n";
+    let docs: Vec<_> =
+        DocumentStream::<Value>::with_separator(input.as_bytes(), Separator::BlankLine).collect();
+    assert_eq!(docs.len(), 3);
+    let first = docs[0].as_ref().unwrap();
+    assert_eq!((first.start, first.end, first.line), (0, 9, 1));
+    assert_eq!(&input[first.start..first.end], "a: 1\nb: 2");
+    match docs[1] {
+        Err(serde_hjson::Error::Syntax(_, line, _)) => assert!(line >= 7),
+        _ => panic!("expected a syntax error"),
+    }
+    let last = docs[2].as_ref().unwrap();
+    assert_eq!(last.line, 9);
+    assert_eq!(last.value.find("d").and_then(Value::as_u64), Some(4));
+
+    let input = b"{a: 1}\n{b: }\n[3]\n";
+    let mut stream = DocumentStream::<Value>::new(input);
+    let first = stream.next().unwrap().unwrap();
+    assert_eq!((first.start, first.end), (0, 6));
+    let resume = stream.offset();
+    assert!(stream.next().unwrap().is_err());
+    let third = stream.next().unwrap().unwrap();
+    assert_eq!((third.start, third.line), (13, 3));
+    assert!(stream.next().is_none());
+
+    stream.seek(resume);
+    assert!(stream.next().unwrap().is_err());
+    assert_eq!(stream.next().unwrap().unwrap().line, 3);
+
+    // errors count columns from the start of the line, not of the document
+    let input = b"1\n\n   \"x\"\n";
+    let mut stream = DocumentStream::<u16>::with_separator(input, Separator::BlankLine);
+    assert_eq!(stream.next().unwrap().unwrap().value, 1);
+    match stream.next().unwrap() {
+        Err(serde_hjson::Error::Data(_, _, line, col)) => assert_eq!((line, col), (3, 4)),
+        _ => panic!("expected a data error"),
+    }
+}
+
+#[test]
+pub fn from_str_at() {
+    #[derive(serde::Deserialize)]
+    struct Env {
+        mode: String,
+        workers: u32,
+    }
+
+    let input = "# config\nservices: {\n  db: {\n    motd:\n      '''\n      welcome\n      '''\n    tags: [\"a\\\"]\", {x: [1, 2]}]\n  }\n  web: {\n    env: {mode: prod\n      workers: 4}\n    ports: [80, 443]\n  }\n}\n";
+    let env: Env = serde_hjson::from_str_at(input, "/services/web/env")
+        .unwrap()
+        .unwrap();
+    assert_eq!((env.mode.as_str(), env.workers), ("prod", 4));
+    let port: Option<u16> = serde_hjson::from_str_at(input, "/services/web/ports/1").unwrap();
+    assert_eq!(port, Some(443));
+    let missing: Option<u16> = serde_hjson::from_str_at(input, "/services/web/ports/2").unwrap();
+    assert_eq!(missing, None);
+    let missing: Option<Value> = serde_hjson::from_str_at(input, "/services/api").unwrap();
+    assert!(missing.is_none());
+    let all: Option<Value> = serde_hjson::from_str_at("{a: {\"b/c\": 1}}", "/a/b~1c").unwrap();
+    assert_eq!(all.and_then(|v| v.as_u64()), Some(1));
+
+    #[derive(serde::Deserialize)]
+    struct Only {
+        b: u32,
+    }
+    let only: Only =
+        serde_hjson::from_str("{a: {x: [1, \"}\"], y: '''\n  ]\n  '''}\nb: 2}").unwrap();
+    assert_eq!(only.b, 2);
+
+    // skipped strings are still checked for invalid escapes
+    assert!(serde_hjson::from_str::<Only>("{a: \"\\q\", b: 2}").is_err());
+    assert!(serde_hjson::from_str_at::<u32>("{a: \"\\u12\", b: 2}", "/b").is_err());
+
+    // a value at the very end of the input
+    let slash: Option<String> = serde_hjson::from_str_at("a: /", "/a").unwrap();
+    assert_eq!(slash.as_deref(), Some("/"));
+}
+
+#[test]
+pub fn from_str_prefix() {
+    let input = "[1, 2]  trailing";
+    let (value, end): (Vec<u32>, usize) = serde_hjson::from_str_prefix(input).unwrap();
+    assert_eq!((value, end), (vec![1, 2], 6));
+
+    let input = "text value\n[3]";
+    let (value, end): (String, usize) = serde_hjson::from_str_prefix(input).unwrap();
+    assert_eq!((value.as_str(), &input[end..]), ("text value", "[3]"));
+
+    let (value, end): (String, usize) = serde_hjson::from_str_prefix("last").unwrap();
+    assert_eq!((value.as_str(), end), ("last", 4));
+
+    assert!(serde_hjson::from_str_prefix::<Value>("{a: 1").is_err());
+}
+
+#[test]
+pub fn from_front_matter() {
+    let input = "---\r\ntitle: Hello\r\ntags: [\"a\", \"b\"]\r\n---\r\nbody\n---\n";
+    let (meta, body): (Option<Value>, &str) = serde_hjson::from_front_matter(input).unwrap();
+    let meta = meta.unwrap();
+    assert_eq!(meta.find("title").and_then(Value::as_str), Some("Hello"));
+    assert_eq!(meta.pointer("/tags/1").and_then(Value::as_str), Some("b"));
+    assert_eq!(body, "body\n---\n");
+
+    let input = "no front-matter\n---\n";
+    let (meta, body): (Option<Value>, &str) = serde_hjson::from_front_matter(input).unwrap();
+    assert!(meta.is_none());
+    assert_eq!(body, input);
+
+    match serde_hjson::from_front_matter::<Value>("---\na: 1\nb: [\n---\n") {
+        Err(serde_hjson::Error::Syntax(_, line, _)) => assert!(line >= 3),
+        _ => panic!("expected a syntax error"),
+    }
+}
+
+#[test]
+pub fn quote_decisions() {
+    let cases = [
+        ("plain", "plain"),
+        ("true", "\"true\""),
+        ("true story", "true story"),
+        ("null # comment", "\"null # comment\""),
+        ("false, or not", "\"false, or not\""),
+        ("12", "\"12\""),
+        ("12 monkeys", "12 monkeys"),
+        (" lead", "\" lead\""),
+        ("trail\u{a0}", "\"trail\u{a0}\""),
+        ("// not a comment", "\"// not a comment\""),
+        ("a \"quote\"", "a \"quote\""),
+        ("\\d+ ", "'''\\d+ '''"),
+        ("bell\u{7}", "\"bell\\u0007\""),
+    ];
+    for (input, expected) in cases.iter() {
+        let text = serde_hjson::to_string(input).unwrap();
+        assert_eq!(text, *expected, "{:?}", input);
+    }
+
+    let keys = [
+        ("key", "key"),
+        ("", "\"\""),
+        ("a b", "\"a b\""),
+        ("a//b", "\"a//b\""),
+        ("it's", "it's"),
+        ("a'''", "\"a'''\""),
+    ];
+    for (key, expected) in keys.iter() {
+        let mut map = Map::new();
+        map.insert(key.to_string(), Value::U64(1));
+        let text = serde_hjson::to_string(&Value::Object(map)).unwrap();
+        assert_eq!(text, format!("{{\n  {}: 1\n}}", expected), "{:?}", key);
+    }
+}
+
+#[test]
+pub fn escape_round_trip() {
+    let chars: Vec<char> = (0..=0x10ffff).filter_map(char::from_u32).collect();
+    for chunk in chars.chunks(0x1000) {
+        // every character as a key and as a value
+        let map: Map<String, Value> = chunk
+            .iter()
+            .map(|&ch| (format!("k{}", ch), Value::String(ch.to_string())))
+            .collect();
+        let text = serde_hjson::to_string(&map).unwrap();
+        let back: Map<String, Value> = serde_hjson::from_str(&text).unwrap();
+        assert_eq!(back.len(), map.len());
+        for ((key, value), (back_key, back_value)) in map.iter().zip(&back) {
+            assert_eq!((key, value), (back_key, back_value));
+        }
+    }
+
+    // strings that look like they fit the multiline format
+    for value in ["a\r\nb\\", " lead\\", "tab\t\\", "''' \\"].iter() {
+        let text = serde_hjson::to_string(&vec![value]).unwrap();
+        assert_eq!(
+            serde_hjson::from_str::<Vec<String>>(&text).unwrap(),
+            vec![*value]
+        );
+    }
+
+    let text = serde_hjson::to_string(&vec!["\u{0}\u{7f}\u{2028}\u{feff}\"\t"]).unwrap();
+    assert_eq!(text, "[\n  \"\\u0000\\u007f\\u2028\\ufeff\\\"\\t\"\n]");
+    let text = serde_hjson::to_string(&'\u{1f}').unwrap();
+    assert_eq!(text, "\"\\u001f\"");
+}
+
+#[test]
+pub fn format_options() {
+    use serde_hjson::{FormatOptions, MultilinePolicy, QuotePolicy};
+
+    let value: Value = serde_hjson::from_str(
+        "{name: café\nlist: [1, \"two\"]\nnested: {re: \"\\\\d+ \"\nml: \"a\\nb\"}}",
+    )
+    .unwrap();
+    let check = |options: FormatOptions, expected: &str| {
+        let text = serde_hjson::to_string_with_options(&value, &options).unwrap();
+        assert_eq!(text, expected);
+        assert_eq!(serde_hjson::from_str::<Value>(&text).unwrap(), value);
+    };
+
+    check(
+        FormatOptions::new().braces_same_line(true),
+        "{\n  name: café\n  list: [\n    1\n    two\n  ]\n  nested: {\n    re: '''\\d+ '''\n    ml:\n      '''\n      a\n      b\n      '''\n  }\n}",
+    );
+    check(
+        FormatOptions::new().root_braces(false).indent("\t").multiline(MultilinePolicy::LineBreaks),
+        "name: café\nlist:\n[\n\t1\n\ttwo\n]\nnested:\n{\n\tre: \"\\\\d+ \"\n\tml:\n\t\t'''\n\t\ta\n\t\tb\n\t\t'''\n}",
+    );
+    check(
+        FormatOptions::new()
+            .crlf(true)
+            .ascii_only(true)
+            .commas(true)
+            .multiline(MultilinePolicy::Never),
+        "{\r\n  name: \"caf\\u00e9\",\r\n  list:\r\n  [\r\n    1,\r\n    \"two\"\r\n  ],\r\n  nested:\r\n  {\r\n    re: \"\\\\d+ \",\r\n    ml: \"a\\nb\"\r\n  }\r\n}",
+    );
+    check(
+        FormatOptions::new().quotes(QuotePolicy::Minimal).braces_same_line(true),
+        "{\n  name: \"café\"\n  list: [\n    1\n    \"two\"\n  ]\n  nested: {\n    re: '''\\d+ '''\n    ml:\n      '''\n      a\n      b\n      '''\n  }\n}",
+    );
+    // keys are quoted only when they need it with both policies, strings always with Minimal
+    let keys: Value = serde_hjson::from_str("{plain: x\n\"a b\": y\n\"#c\": z\n}").unwrap();
+    let text = serde_hjson::to_string_with_options(&keys, &FormatOptions::new()).unwrap();
+    assert_eq!(text, "{\n  plain: x\n  \"a b\": y\n  \"#c\": z\n}");
+    let minimal = FormatOptions::new().quotes(QuotePolicy::Minimal);
+    let text = serde_hjson::to_string_with_options(&keys, &minimal).unwrap();
+    assert_eq!(
+        text,
+        "{\n  plain: \"x\"\n  \"a b\": \"y\"\n  \"#c\": \"z\"\n}"
+    );
+    let text = serde_hjson::to_string_with_options(
+        &vec!["😀"],
+        &FormatOptions::new()
+            .quotes(QuotePolicy::Always)
+            .ascii_only(true),
+    )
+    .unwrap();
+    assert_eq!(text, "[\n  \"\\ud83d\\ude00\"\n]");
+    let text = serde_hjson::to_string_with_options(
+        &value.find("nested").unwrap().clone(),
+        &FormatOptions::new()
+            .quotes(QuotePolicy::Always)
+            .multiline(MultilinePolicy::Never),
+    )
+    .unwrap();
+    assert_eq!(text, "{\n  \"re\": \"\\\\d+ \"\n  \"ml\": \"a\\nb\"\n}");
+}
+
+#[test]
+pub fn max_width_layout() {
+    use serde_hjson::color::{ColorFormatter, ColorTheme};
+    use serde_hjson::{Commented, FormatOptions, HjsonFormatter, Serializer};
+
+    let value: Value = serde_hjson::from_str(
+        "{server: {host: localhost\nports: [80, 443]\ntags: [\"a b\", \"ü\"]}\nlong: [\"aaaaaaaaaaaaaaaaaaaaaaaaa\", \"bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\", {x: 1, y: [1, 2]}]\nml: \"a\\nb\"\nempty: []}",
+    )
+    .unwrap();
+    let check = |options: FormatOptions, expected: &str| {
+        let text = serde_hjson::to_string_with_options(&value, &options).unwrap();
+        assert_eq!(text, expected);
+        assert_eq!(serde_hjson::from_str::<Value>(&text).unwrap(), value);
+    };
+
+    check(
+        FormatOptions::new().max_width(40),
+        "{\n  server:\n  {\n    host: localhost\n    ports: [80, 443]\n    tags: [\"a b\", \"ü\"]\n  }\n  long:\n  [\n    aaaaaaaaaaaaaaaaaaaaaaaaa\n    bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\n    {x: 1, y: [1, 2]}\n  ]\n  ml:\n    '''\n    a\n    b\n    '''\n  empty: []\n}",
+    );
+    check(
+        FormatOptions::new().max_width(40).braces_same_line(true).commas(true),
+        "{\n  server: {\n    host: \"localhost\",\n    ports: [80, 443],\n    tags: [\"a b\", \"ü\"]\n  },\n  long: [\n    \"aaaaaaaaaaaaaaaaaaaaaaaaa\",\n    \"bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\",\n    {x: 1, y: [1, 2]}\n  ],\n  ml:\n    '''\n    a\n    b\n    ''',\n  empty: []\n}",
+    );
+    // the limit includes the indentation and the key
+    let server = value.find("server").unwrap();
+    let inline = "{host: \"localhost\", ports: [80, 443], tags: [\"a b\", \"ü\"]}";
+    let options = FormatOptions::new().max_width(inline.chars().count());
+    assert_eq!(
+        serde_hjson::to_string_with_options(server, &options).unwrap(),
+        inline
+    );
+    let options = FormatOptions::new().max_width(inline.chars().count() - 1);
+    assert_eq!(
+        serde_hjson::to_string_with_options(server, &options).unwrap(),
+        "{\n  host: localhost\n  ports: [80, 443]\n  tags: [\"a b\", \"ü\"]\n}"
+    );
+    let options = FormatOptions::new().root_braces(false).max_width(80);
+    assert_eq!(
+        serde_hjson::to_string_with_options(server, &options).unwrap(),
+        "host: localhost\nports: [80, 443]\ntags: [\"a b\", \"ü\"]"
+    );
+
+    // typed values keep their comments, field order and float precision
+    #[derive(serde::Serialize)]
+    struct Limits {
+        zone: Commented<u32>,
+        ratio: f32,
+        ports: Vec<u16>,
+        pair: Commented<(u8, u8)>,
+    }
+
+    let limits = Limits {
+        zone: Commented::trailing(3, "eu"),
+        ratio: 0.1,
+        ports: vec![80, 443],
+        pair: Commented::new((1, 2), "kept"),
+    };
+    let expected = "{\n  zone: 3 # eu\n  ratio: 0.1\n  ports: [80, 443]\n  # kept\n  pair:\n  [\n    1\n    2\n  ]\n}";
+    let options = FormatOptions::new().max_width(80);
+    assert_eq!(
+        serde_hjson::to_string_with_options(&limits, &options).unwrap(),
+        expected
+    );
+
+    // any serializer whose formatter has the option lays out nested values
+    let mut out = Vec::new();
+    let formatter =
+        ColorFormatter::with_theme(HjsonFormatter::with_options(options), ColorTheme::plain());
+    let mut ser = Serializer::with_formatter(&mut out, formatter);
+    serde::Serialize::serialize(&limits, &mut ser).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+pub fn formatter_hooks() {
+    use serde_hjson::ser::{Formatter, HjsonFormatter, Serializer};
+
+    // Records the paths passed to the hooks and writes numbers as hex.
+    struct Recorder<'a> {
+        inner: HjsonFormatter,
+        calls: &'a mut Vec<String>,
+    }
+
+    impl<'a> Formatter for Recorder<'a> {
+        fn open<W: io::Write>(&mut self, w: &mut W, ch: u8) -> Result<()> {
+            self.inner.open(w, ch)
+        }
+        fn comma<W: io::Write>(&mut self, w: &mut W, first: bool) -> Result<()> {
+            self.inner.comma(w, first)
+        }
+        fn colon<W: io::Write>(&mut self, w: &mut W) -> Result<()> {
+            self.inner.colon(w)
+        }
+        fn close<W: io::Write>(&mut self, w: &mut W, ch: u8) -> Result<()> {
+            self.inner.close(w, ch)
+        }
+        fn newline<W: io::Write>(&mut self, w: &mut W, add_indent: i32) -> Result<()> {
+            self.inner.newline(w, add_indent)
+        }
+        fn start_value<W: io::Write>(&mut self, w: &mut W) -> Result<()> {
+            self.inner.start_value(w)
+        }
+        fn write_key<W: io::Write>(&mut self, w: &mut W, path: &str, key: &str) -> Result<()> {
+            self.calls.push(format!("key {}", path));
+            self.inner.write_key(w, path, key)
+        }
+        fn begin_object_value<W: io::Write>(&mut self, _w: &mut W, path: &str) -> Result<()> {
+            self.calls.push(format!("begin {}", path));
+            Ok(())
+        }
+        fn end_object_value<W: io::Write>(&mut self, _w: &mut W, path: &str) -> Result<()> {
+            self.calls.push(format!("end {}", path));
+            Ok(())
+        }
+        fn write_string<W: io::Write>(&mut self, w: &mut W, path: &str, value: &str) -> Result<()> {
+            self.calls.push(format!("string {}", path));
+            self.inner.write_string(w, path, value)
+        }
+        fn write_number<W: io::Write>(&mut self, w: &mut W, path: &str, value: &str) -> Result<()> {
+            self.calls.push(format!("number {}", path));
+            self.start_value(w)?;
+            match value.parse::<u64>() {
+                Ok(n) => write!(w, "0x{:x}", n).map_err(From::from),
+                Err(_) => w.write_all(value.as_bytes()).map_err(From::from),
+            }
+        }
+        fn write_bool<W: io::Write>(&mut self, w: &mut W, path: &str, value: bool) -> Result<()> {
+            self.calls.push(format!("bool {}", path));
+            self.inner.write_bool(w, path, value)
+        }
+        fn write_null<W: io::Write>(&mut self, w: &mut W, path: &str) -> Result<()> {
+            self.calls.push(format!("null {}", path));
+            self.inner.write_null(w, path)
+        }
+    }
+
+    #[derive(serde::Serialize)]
+    enum Shape {
+        Circle(f64),
+        Rect { w: u8, h: u8 },
+        Pair(bool, bool),
+    }
+
+    #[derive(serde::Serialize)]
+    struct Doc {
+        #[serde(rename = "a/b~c")]
+        escaped: Vec<Option<u32>>,
+        shapes: Vec<Shape>,
+        name: &'static str,
+    }
+
+    let doc = Doc {
+        escaped: vec![Some(255), None],
+        shapes: vec![
+            Shape::Circle(f64::NAN),
+            Shape::Rect { w: 1, h: 2 },
+            Shape::Pair(true, false),
+        ],
+        name: "x",
+    };
+    let mut out = Vec::new();
+    let mut calls = Vec::new();
+    let mut ser = Serializer::with_formatter(
+        &mut out,
+        Recorder {
+            inner: HjsonFormatter::new(),
+            calls: &mut calls,
+        },
+    );
+    serde::Serialize::serialize(&doc, &mut ser).unwrap();
+    assert_eq!(
+        calls,
+        [
+            "key /a~1b~0c",
+            "begin /a~1b~0c",
+            "number /a~1b~0c/0",
+            "null /a~1b~0c/1",
+            "end /a~1b~0c",
+            "key /shapes",
+            "begin /shapes",
+            "key /shapes/0/Circle",
+            "begin /shapes/0/Circle",
+            "null /shapes/0/Circle",
+            "end /shapes/0/Circle",
+            "key /shapes/1/Rect",
+            "begin /shapes/1/Rect",
+            "key /shapes/1/Rect/w",
+            "begin /shapes/1/Rect/w",
+            "number /shapes/1/Rect/w",
+            "end /shapes/1/Rect/w",
+            "key /shapes/1/Rect/h",
+            "begin /shapes/1/Rect/h",
+            "number /shapes/1/Rect/h",
+            "end /shapes/1/Rect/h",
+            "end /shapes/1/Rect",
+            "key /shapes/2/Pair",
+            "begin /shapes/2/Pair",
+            "bool /shapes/2/Pair/0",
+            "bool /shapes/2/Pair/1",
+            "end /shapes/2/Pair",
+            "end /shapes",
+            "key /name",
+            "begin /name",
+            "string /name",
+            "end /name",
+        ]
+    );
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("\n    0xff\n"), "{}", text);
+    assert!(text.contains("\n        w: 0x1\n"), "{}", text);
+}
+
+#[test]
+pub fn formatter_inline_strings() {
+    use serde_hjson::ser::{Formatter, HjsonFormatter, Serializer};
+    use serde_hjson::Commented;
+
+    // Writes strings in upper case and records the single line values.
+    struct Upper<'a> {
+        inner: HjsonFormatter,
+        calls: &'a mut Vec<String>,
+    }
+
+    impl<'a> Formatter for Upper<'a> {
+        fn open<W: io::Write>(&mut self, w: &mut W, ch: u8) -> Result<()> {
+            self.inner.open(w, ch)
+        }
+        fn comma<W: io::Write>(&mut self, w: &mut W, first: bool) -> Result<()> {
+            self.inner.comma(w, first)
+        }
+        fn colon<W: io::Write>(&mut self, w: &mut W) -> Result<()> {
+            self.inner.colon(w)
+        }
+        fn close<W: io::Write>(&mut self, w: &mut W, ch: u8) -> Result<()> {
+            self.inner.close(w, ch)
+        }
+        fn newline<W: io::Write>(&mut self, w: &mut W, add_indent: i32) -> Result<()> {
+            self.inner.newline(w, add_indent)
+        }
+        fn start_value<W: io::Write>(&mut self, w: &mut W) -> Result<()> {
+            self.inner.start_value(w)
+        }
+        fn begin_inline<W: io::Write>(&mut self, w: &mut W, path: &str) -> Result<()> {
+            self.calls.push(format!("inline {}", path));
+            self.inner.begin_inline(w, path)
+        }
+        fn end_inline<W: io::Write>(&mut self, w: &mut W, path: &str) -> Result<()> {
+            self.inner.end_inline(w, path)
+        }
+        fn write_string<W: io::Write>(&mut self, w: &mut W, path: &str, value: &str) -> Result<()> {
+            self.inner.write_string(w, path, &value.to_uppercase())
+        }
+    }
+
+    #[derive(serde::Serialize)]
+    struct Doc {
+        grade: char,
+        note: Commented<String>,
+        lines: Commented<String>,
+        plain: String,
+    }
+
+    let doc = Doc {
+        grade: 'a',
+        note: Commented::trailing("ok".to_owned(), "checked"),
+        lines: Commented::trailing("one\ntwo".to_owned(), "split"),
+        plain: "done".to_owned(),
+    };
+    let mut out = Vec::new();
+    let mut calls = Vec::new();
+    let formatter = Upper {
+        inner: HjsonFormatter::new(),
+        calls: &mut calls,
+    };
+    let mut ser = Serializer::with_formatter(&mut out, formatter);
+    serde::Serialize::serialize(&doc, &mut ser).unwrap();
+    assert_eq!(calls, ["inline /grade", "inline /note", "inline /lines"]);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "{\n  grade: \"A\"\n  note: \"OK\" # checked\n  lines: \"ONE\\nTWO\" # split\n  plain: DONE\n}"
+    );
+}
+
+#[test]
+pub fn key_policies() {
+    use serde::Serialize;
+    use serde_hjson::{JsonFormatter, Serializer};
+
+    let value: Value = serde_hjson::from_str(
+        "{zone: \"b\"\nservers: [{name: \"a\", password: \"x\", port: null}, {password: \"y\", name: \"b\"}]\nauth: {token: \"t\", user: \"u\"}\ncache: null\ndebug_level: 3\ndebug_trace: true}",
+    )
+    .unwrap();
+    type JsonSerializer<'a> = Serializer<&'a mut Vec<u8>, JsonFormatter>;
+    let json = |configure: &dyn Fn(JsonSerializer) -> JsonSerializer| {
+        let mut out = Vec::new();
+        let mut ser = configure(Serializer::with_formatter(
+            &mut out,
+            JsonFormatter::compact(),
+        ));
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+
+    assert_eq!(
+        json(&|ser| ser.sort_keys(true)),
+        r#"{"auth":{"token":"t","user":"u"},"cache":null,"debug_level":3,"debug_trace":true,"servers":[{"name":"a","password":"x","port":null},{"name":"b","password":"y"}],"zone":"b"}"#
+    );
+    assert_eq!(
+        json(&|ser| ser.sort_keys_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)))),
+        r#"{"debug_level":3,"debug_trace":true,"servers":[{"password":"x","name":"a","port":null},{"password":"y","name":"b"}],"cache":null,"auth":{"token":"t","user":"u"},"zone":"b"}"#
+    );
+    assert_eq!(
+        json(&|ser| ser.skip_keys(&["debug_*", "/auth/token"]).skip_nulls(true)),
+        r#"{"zone":"b","servers":[{"name":"a","password":"x"},{"password":"y","name":"b"}],"auth":{"user":"u"}}"#
+    );
+    assert_eq!(
+        json(&|ser| ser
+            .redact_keys(&["/servers/*/password", "token"], "<redacted>")
+            .skip_keys(&["/*/user", "/d*"])),
+        r#"{"zone":"b","servers":[{"name":"a","password":"<redacted>","port":null},{"password":"<redacted>","name":"b"}],"auth":{"token":"<redacted>"},"cache":null}"#
+    );
+    // patterns with a * do not match across path segments
+    assert_eq!(json(&|ser| ser.skip_keys(&["/*"])), "{}");
+    assert_eq!(
+        json(&|ser| ser.skip_keys(&["/*/name", "/*password"])),
+        json(&|ser| ser)
+    );
+
+    // sorted members are laid out like the others
+    let mut out = Vec::new();
+    let mut ser = Serializer::new(&mut out)
+        .sort_keys(true)
+        .skip_nulls(true)
+        .skip_keys(&["servers"]);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "{\n  auth:\n  {\n    token: t\n    user: u\n  }\n  debug_level: 3\n  debug_trace: true\n  zone: b\n}"
+    );
+}
+
+#[test]
+pub fn non_string_keys() {
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    let ports: BTreeMap<i32, &str> = [(-1, "none"), (80, "http"), (8080, "alt")].into();
+    let text = serde_hjson::to_string(&ports).unwrap();
+    assert_eq!(text, "{\n  -1: none\n  80: http\n  8080: alt\n}");
+    let back: BTreeMap<i32, String> = serde_hjson::from_str(&text).unwrap();
+    assert_eq!(back.len(), 3);
+    assert_eq!(back[&8080], "alt");
+
+    let flags: BTreeMap<bool, u8> = [(false, 0), (true, 1)].into();
+    let text = serde_hjson::to_string(&flags).unwrap();
+    assert_eq!(text, "{\n  false: 0\n  true: 1\n}");
+    assert_eq!(
+        serde_hjson::from_str::<BTreeMap<bool, u8>>(&text).unwrap(),
+        flags
+    );
+
+    let chars: BTreeMap<char, u8> = [('a', 1), (':', 2)].into();
+    let text = serde_hjson::to_string(&chars).unwrap();
+    assert_eq!(text, "{\n  \":\": 2\n  a: 1\n}");
+    assert_eq!(
+        serde_hjson::from_str::<BTreeMap<char, u8>>(&text).unwrap(),
+        chars
+    );
+
+    let colors: BTreeMap<Color, u8> = [(Color::Red, 1), (Color::Green, 2)].into();
+    let text = serde_hjson::to_string(&colors).unwrap();
+    assert_eq!(text, "{\n  Red: 1\n  Green: 2\n}");
+    assert_eq!(
+        serde_hjson::from_str::<BTreeMap<Color, u8>>(&text).unwrap(),
+        colors
+    );
+
+    // keys are parsed according to the target type, also from a Value
+    let text = "{8080: {host: \"a\"}\n\"443\": {host: \"b\"}}";
+    let servers: BTreeMap<u16, BTreeMap<String, String>> = serde_hjson::from_str(text).unwrap();
+    assert_eq!(servers.keys().collect::<Vec<_>>(), [&443, &8080]);
+    let value = serde_hjson::to_value(&ports).unwrap();
+    assert_eq!(value.find("-1").and_then(|v| v.as_str()), Some("none"));
+    let back: BTreeMap<i64, String> = serde_hjson::from_value(value).unwrap();
+    assert_eq!(back[&-1], "none");
+
+    assert!(serde_hjson::from_str::<BTreeMap<u8, u8>>("{256: 1}").is_err());
+    assert!(serde_hjson::from_str::<BTreeMap<u8, u8>>("{x: 1}").is_err());
+    let float_keys: BTreeMap<String, u8> = serde_hjson::from_str("{1.5: 1}").unwrap();
+    assert_eq!(float_keys["1.5"], 1);
+    assert!(
+        serde_hjson::to_string(&[((1, 2), 3)].iter().cloned().collect::<BTreeMap<_, _>>()).is_err()
+    );
+}
+
+#[test]
+pub fn bytes_format() {
+    use serde_hjson::{BytesFormat, FormatOptions};
+    use std::collections::BTreeMap;
+    use std::fmt;
+
+    #[derive(Debug, PartialEq)]
+    struct Bytes(Vec<u8>);
+
+    impl serde::Serialize for Bytes {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for Bytes {
+        fn deserialize<D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Bytes, D::Error> {
+            struct BytesVisitor;
+            impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+                type Value = Bytes;
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("bytes")
+                }
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> std::result::Result<Bytes, E> {
+                    Ok(Bytes(v))
+                }
+                fn visit_str<E>(self, v: &str) -> std::result::Result<Bytes, E> {
+                    Ok(Bytes(v.as_bytes().to_vec()))
+                }
+                fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                    self,
+                    mut seq: A,
+                ) -> std::result::Result<Bytes, A::Error> {
+                    let mut bytes = Vec::new();
+                    while let Some(b) = seq.next_element()? {
+                        bytes.push(b);
+                    }
+                    Ok(Bytes(bytes))
+                }
+            }
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+
+    let short = Bytes(b"hi!?".to_vec());
+    let to_string = |bytes: &Bytes, options: FormatOptions| {
+        serde_hjson::to_string_with_options(bytes, &options).unwrap()
+    };
+    assert_eq!(
+        to_string(&short, FormatOptions::new()),
+        "[\n  104\n  105\n  33\n  63\n]"
+    );
+    assert_eq!(
+        to_string(&short, FormatOptions::new().bytes(BytesFormat::Base64)),
+        "aGkhPw=="
+    );
+    assert_eq!(
+        to_string(&short, FormatOptions::new().bytes(BytesFormat::Hex)),
+        "6869213f"
+    );
+
+    // strings are only decoded by a deserializer that is told the format
+    let decode = |text: &str, format: BytesFormat| {
+        let mut de = serde_hjson::Deserializer::new(text.bytes());
+        de.set_bytes_format(format);
+        let bytes = <Bytes as serde::Deserialize>::deserialize(&mut de)?;
+        de.end().map(|()| bytes)
+    };
+
+    // long data is split into lines of a multiline string
+    let long = Bytes((0..=255).collect());
+    let text = to_string(&long, FormatOptions::new().bytes(BytesFormat::Base64));
+    assert!(text.starts_with("\n  '''\n  AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4\n  OTo7"), "{}", text);
+    assert_eq!(decode(&text, BytesFormat::Base64).unwrap(), long);
+    let text = to_string(
+        &long,
+        FormatOptions::new()
+            .bytes(BytesFormat::Base64)
+            .multiline(serde_hjson::MultilinePolicy::Never),
+    );
+    assert_eq!(text.len(), 344);
+    assert_eq!(decode(&text, BytesFormat::Base64).unwrap(), long);
+    let text = to_string(&long, FormatOptions::new().bytes(BytesFormat::Hex));
+    assert_eq!(decode(&text, BytesFormat::Hex).unwrap(), long);
+
+    // by default strings give their UTF-8 bytes, arrays are always accepted
+    assert_eq!(
+        serde_hjson::from_str::<Bytes>("aGk=").unwrap(),
+        Bytes(b"aGk=".to_vec())
+    );
+    assert_eq!(
+        decode("aGk=", BytesFormat::Array).unwrap(),
+        Bytes(b"aGk=".to_vec())
+    );
+    for format in [BytesFormat::Array, BytesFormat::Base64, BytesFormat::Hex] {
+        assert_eq!(decode("[1, 2]", format).unwrap(), Bytes(vec![1, 2]));
+        assert_eq!(decode("\"\"", format).unwrap(), Bytes(vec![]));
+    }
+    for padding in ["aGk=", "aGk", "aG k="] {
+        assert_eq!(
+            decode(padding, BytesFormat::Base64).unwrap(),
+            Bytes(b"hi".to_vec())
+        );
+    }
+    for invalid in ["a", "aG=k", "a*b"] {
+        assert!(decode(invalid, BytesFormat::Base64).is_err(), "{}", invalid);
+    }
+
+    // the Value serializer and deserializer, also for nested values
+    use serde_hjson::value::{BytesDeserializer, BytesSerializer};
+    let value =
+        serde::Serialize::serialize(&short, BytesSerializer::new(BytesFormat::Base64)).unwrap();
+    assert_eq!(value, Value::String("aGkhPw==".to_owned()));
+    assert_eq!(
+        serde_hjson::from_value::<Bytes>(value.clone()).unwrap(),
+        Bytes(b"aGkhPw==".to_vec())
+    );
+    let de = BytesDeserializer::new(value, BytesFormat::Base64);
+    assert_eq!(
+        <Bytes as serde::Deserialize>::deserialize(de).unwrap(),
+        short
+    );
+    let nested: Value = serde_hjson::from_str("{key: [\"6869213f\"]}").unwrap();
+    let de = BytesDeserializer::new(nested, BytesFormat::Hex);
+    let map: BTreeMap<String, Vec<Bytes>> = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(map["key"], [Bytes(b"hi!?".to_vec())]);
+    assert_eq!(
+        serde_hjson::to_value(&short)
+            .unwrap()
+            .as_array()
+            .map(|a| a.len()),
+        Some(4)
+    );
+    let laid_out = to_string(
+        &short,
+        FormatOptions::new().bytes(BytesFormat::Hex).max_width(80),
+    );
+    assert_eq!(laid_out, "6869213f");
+}
+
+#[test]
+pub fn commented_values() {
+    use serde_hjson::Commented;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: Commented<String>,
+        tags: Vec<Commented<String>>,
+        retries: Commented<Option<u32>>,
+        nested: Commented<Vec<u32>>,
+    }
+
+    let config = Config {
+        name: Commented::trailing("my service".to_owned(), "shown in logs"),
+        tags: vec![
+            Commented::new("web".to_owned(), "first\n\nsecond"),
+            "api".to_owned().into(),
+        ],
+        retries: Commented::new(None, "unlimited"),
+        nested: Commented {
+            value: vec![1],
+            comment: Some("above".to_owned()),
+            trailing: Some("after".to_owned()),
+        },
+    };
+    let text = serde_hjson::to_string(&config).unwrap();
+    assert_eq!(
+        text,
+        "{\n  name: \"my service\" # shown in logs\n  tags:\n  [\n    # first\n    #\n    # second\n    web\n    api\n  ]\n  # unlimited\n  retries: null\n  # above\n  nested:\n  [\n    1\n  ] # after\n}"
+    );
+    let parsed: Config = serde_hjson::from_str(&text).unwrap();
+    assert_eq!(*parsed.name, "my service");
+    assert_eq!(parsed.tags[0].value, "web");
+    assert_eq!(parsed.nested.into_inner(), vec![1]);
+
+    // a root comment, skipped and sorted members
+    let root = Commented::new(config, "generated");
+    let mut buf = Vec::new();
+    let mut ser = serde_hjson::Serializer::new(&mut buf)
+        .sort_keys(true)
+        .skip_nulls(true);
+    serde::Serialize::serialize(&root, &mut ser).unwrap();
+    let text = String::from_utf8(buf).unwrap();
+    assert!(
+        text.starts_with("# generated\n{\n  name: \"my service\" # shown in logs\n  # above\n"),
+        "{}",
+        text
+    );
+    assert!(!text.contains("unlimited"), "{}", text);
+
+    // other serializers only see the value
+    let json = serde_hjson::to_json_string(&root).unwrap();
+    assert!(
+        json.starts_with("{\"name\":\"my service\",\"tags\":[\"web\""),
+        "{}",
+        json
+    );
+    assert_eq!(
+        serde_json::to_string(&Commented::new(1, "one")).unwrap(),
+        "1"
+    );
+    assert_eq!(
+        serde_hjson::to_value(&root).unwrap().pointer("/tags/1"),
+        Some(&Value::String("api".to_owned()))
+    );
+}
+
+#[test]
+pub fn documented_output() {
+    use serde_hjson::HjsonDoc;
+    use std::collections::BTreeMap;
+
+    /// The service configuration.
+    ///
+    /// Generated from the code.
+    #[derive(serde::Serialize, HjsonDoc)]
+    #[serde(rename_all = "camelCase")]
+    struct Config {
+        /// The port to listen on.
+        listen_port: u16,
+        /// How much to log.
+        level: Level,
+        #[serde(rename = "upstream")]
+        servers: Vec<Server>,
+        /// Named backups.
+        backups: BTreeMap<String, Server>,
+        #[serde(flatten)]
+        limits: Limits,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        cache: Option<u8>,
+        mode: Mode,
+    }
+
+    #[derive(serde::Serialize, HjsonDoc)]
+    #[serde(rename_all = "lowercase")]
+    #[allow(dead_code)]
+    enum Level {
+        /// Only errors.
+        Error,
+        Warn,
+        /// Everything,
+        /// including requests.
+        Debug,
+    }
+
+    #[derive(serde::Serialize, HjsonDoc)]
+    struct Server {
+        /// The host name.
+        host: String,
+        weight: Weight,
+    }
+
+    /// How often the server is picked.
+    #[derive(serde::Serialize, HjsonDoc)]
+    struct Weight(u8);
+
+    #[derive(serde::Serialize, HjsonDoc)]
+    struct Limits {
+        /// Requests per second.
+        rate: u32,
+    }
+
+    #[derive(serde::Serialize, HjsonDoc)]
+    enum Mode {
+        /// Forwards requests.
+        Proxy {
+            /// Seconds to wait.
+            timeout: u32,
+        },
+    }
+
+    let server = || Server {
+        host: "localhost".to_owned(),
+        weight: Weight(1),
+    };
+    let config = Config {
+        listen_port: 8080,
+        level: Level::Warn,
+        servers: vec![server()],
+        backups: vec![("first".to_owned(), server())].into_iter().collect(),
+        limits: Limits { rate: 10 },
+        cache: None,
+        mode: Mode::Proxy { timeout: 5 },
+    };
+    assert_eq!(
+        serde_hjson::to_string_documented(&config).unwrap(),
+        r#"# The service configuration.
+#
+# Generated from the code.
+{
+  # The port to listen on.
+  listenPort: 8080
+  # How much to log.
+  # - error: Only errors.
+  # - warn
+  # - debug: Everything,
+  #   including requests.
+  level: warn
+  upstream:
+  [
+    {
+      # The host name.
+      host: localhost
+      # How often the server is picked.
+      weight: 1
+    }
+  ]
+  # Named backups.
+  backups:
+  {
+    first:
+    {
+      # The host name.
+      host: localhost
+      # How often the server is picked.
+      weight: 1
+    }
+  }
+  # Requests per second.
+  rate: 10
+  # - Proxy: Forwards requests.
+  mode:
+  {
+    Proxy:
+    {
+      # Seconds to wait.
+      timeout: 5
+    }
+  }
+}"#
+    );
+    let doc = Config::hjson_doc();
+    let names: Vec<_> = doc.members.iter().map(|member| member.name).collect();
+    assert_eq!(
+        names,
+        ["listenPort", "level", "upstream", "backups", "rate", "mode"]
+    );
+}
+
+#[test]
+pub fn streaming_writers() {
+    use serde_hjson::{JsonFormatter, Serializer};
+
+    let records: Vec<Map<String, Value>> = (0..3)
+        .map(|i| {
+            let mut record = Map::new();
+            record.insert("id".to_owned(), Value::U64(i));
+            record.insert("tags".to_owned(), Value::Array(vec![]));
+            record
+        })
+        .collect();
+
+    let mut writer = Vec::new();
+    let mut ser = Serializer::new(&mut writer);
+    let mut seq = ser.seq_writer().unwrap();
+    for record in &records {
+        seq.write_element(record).unwrap();
+    }
+    seq.end().unwrap();
+    assert_eq!(
+        String::from_utf8(writer).unwrap(),
+        serde_hjson::to_string(&records).unwrap()
+    );
+
+    let mut writer = Vec::new();
+    let mut ser = Serializer::with_formatter(&mut writer, JsonFormatter::compact());
+    let mut map = ser.map_writer().unwrap();
+    map.write_entry("count", &2).unwrap();
+    map.write_entry(&7, &records[..1]).unwrap();
+    map.end().unwrap();
+    assert_eq!(
+        String::from_utf8(writer).unwrap(),
+        r#"{"count":2,"7":[{"id":0,"tags":[]}]}"#
+    );
+
+    // empty, and with sorted keys
+    let mut writer = Vec::new();
+    let mut ser = Serializer::new(&mut writer).sort_keys(true);
+    ser.seq_writer().unwrap().end().unwrap();
+    let mut map = ser.map_writer().unwrap();
+    map.write_entry("b", &1).unwrap();
+    map.write_entry("a", &2).unwrap();
+    map.end().unwrap();
+    let text = String::from_utf8(writer).unwrap();
+    assert_eq!(text, "[\n]{\n  a: 2\n  b: 1\n}");
+    assert_eq!(
+        serde_hjson::from_str::<Vec<u8>>(&text[..3]).unwrap(),
+        Vec::<u8>::new()
+    );
+}
+
+#[test]
+pub fn source_map() {
+    use serde_hjson::Serializer;
+
+    let value: Value = serde_hjson::from_str(
+        r#"{
+            name: "web"
+            ports: [80, 443]
+            limits: { rate: 10, burst: "x y" }
+        }"#,
+    )
+    .unwrap();
+    let check = |text: &str, map: &serde_hjson::SourceMap, pointer: &str, expected: &str| {
+        let span = map.get(pointer).unwrap();
+        assert_eq!(&text[span.start..span.end], expected, "{}", pointer);
+        let line = text.lines().nth(span.line - 1).unwrap();
+        assert!(line[span.column - 1..].starts_with(expected.lines().next().unwrap()));
+        let end_line = text.lines().nth(span.end_line - 1).unwrap();
+        assert_eq!(end_line.len() + 1, span.end_column, "{}", pointer);
+    };
+
+    let mut writer = Vec::new();
+    let map = serde_hjson::to_writer_with_source_map(&mut writer, &value).unwrap();
+    let text = String::from_utf8(writer).unwrap();
+    assert_eq!(map.len(), 8);
+    check(&text, &map, "", &text);
+    check(&text, &map, "/name", "name: web");
+    check(&text, &map, "/ports", "ports:\n  [\n    80\n    443\n  ]");
+    check(&text, &map, "/ports/1", "443");
+    check(&text, &map, "/limits/burst", "burst: x y");
+
+    // members with sorted keys are collected before they are written
+    let mut writer = Vec::new();
+    let mut ser = Serializer::new(&mut writer)
+        .sort_keys(true)
+        .record_source_map(true);
+    serde::Serialize::serialize(&value, &mut ser).unwrap();
+    let map = ser.source_map().unwrap();
+    let text = String::from_utf8(writer).unwrap();
+    assert!(
+        text.starts_with("{\n  limits:\n  {\n    burst:"),
+        "{}",
+        text
+    );
+    check(&text, &map, "/limits/rate", "rate: 10");
+    check(&text, &map, "/name", "name: web");
+    check(&text, &map, "/ports/0", "80");
+    assert_eq!(
+        map.iter().map(|(pointer, _)| pointer).collect::<Vec<_>>(),
+        [
+            "",
+            "/limits",
+            "/limits/burst",
+            "/limits/rate",
+            "/name",
+            "/ports",
+            "/ports/0",
+            "/ports/1"
+        ]
+    );
+    assert_eq!(Serializer::new(Vec::new()).source_map(), None);
+}
+
+#[test]
+pub fn float_format() {
+    use serde_hjson::FormatOptions;
+
+    let cases: &[(f64, &str)] = &[
+        (0.0, "0"),
+        (-0.0, "0"),
+        (100.0, "100"),
+        (-1.5, "-1.5"),
+        (0.1 + 0.2, "0.30000000000000004"),
+        (123e18, "123000000000000000000"),
+        (1e21, "1e+21"),
+        (1.5e300, "1.5e+300"),
+        (0.000001, "0.000001"),
+        (1.25e-7, "1.25e-7"),
+        (5e-324, "5e-324"),
+        (f64::MAX, "1.7976931348623157e+308"),
+    ];
+    for &(value, expected) in cases {
+        assert_eq!(serde_hjson::to_string(&value).unwrap(), expected);
+        assert_eq!(serde_hjson::to_json_string(&value).unwrap(), expected);
+    }
+    assert_eq!(serde_hjson::to_string(&0.1f32).unwrap(), "0.1");
+    assert_eq!(serde_hjson::to_string(&16777216f32).unwrap(), "16777216");
+
+    // integral floats stay floats with a `.0`
+    let options = FormatOptions::new().float_point(true);
+    let values = vec![
+        Value::F64(1.0),
+        Value::F64(-0.0),
+        Value::F64(1e21),
+        Value::U64(1),
+    ];
+    let text = serde_hjson::to_string_with_options(&values, &options).unwrap();
+    assert_eq!(text, "[\n  1.0\n  0.0\n  1e+21\n  1\n]");
+    let parsed: Vec<Value> = serde_hjson::from_str(&text).unwrap();
+    assert_eq!(
+        parsed,
+        [
+            Value::F64(1.0),
+            Value::F64(0.0),
+            Value::F64(1e21),
+            Value::U64(1)
+        ]
+    );
+    let text = serde_hjson::to_string(&values).unwrap();
+    let parsed: Vec<Value> = serde_hjson::from_str(&text).unwrap();
+    assert_eq!(parsed[0], Value::U64(1));
+}
+
+#[test]
+pub fn error_path() {
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Config {
+        servers: Vec<Server>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    enum Mode {
+        Fixed { port: u16 },
+    }
+
+    fn path<T: std::fmt::Debug>(result: serde_hjson::Result<T>) -> String {
+        result.unwrap_err().path().unwrap().to_owned()
+    }
+
+    let servers =
+        "servers: [\n  {host: \"a\", port: 1}\n  {host: \"b\", port: 2}\n  {host: \"c\", port: \"x\"}\n]";
+    let err = serde_hjson::from_str::<Config>(servers).unwrap_err();
+    assert_eq!(err.path(), Some("/servers/2/port"));
+    assert_eq!(
+        err.to_string(),
+        "/servers/2/port: invalid type: string \"x\", expected u16 at line 4 column 21"
+    );
+    let braced = format!("{{{}}}", servers);
+    assert_eq!(
+        path(serde_hjson::from_str::<Config>(&braced)),
+        "/servers/2/port"
+    );
+    let value: Value = serde_hjson::from_str(servers).unwrap();
+    assert_eq!(
+        path(serde_hjson::from_value::<Config>(value)),
+        "/servers/2/port"
+    );
+
+    // missing fields are reported at the object, unknown fields at the key
+    let missing = "servers: [{host: \"a\"}]";
+    assert_eq!(path(serde_hjson::from_str::<Config>(missing)), "/servers/0");
+    let value: Value = serde_hjson::from_str(missing).unwrap();
+    assert_eq!(path(serde_hjson::from_value::<Config>(value)), "/servers/0");
+    let unknown = "servers: [{host: \"a\", port: 1, user: \"b\"}]";
+    assert_eq!(
+        path(serde_hjson::from_str::<Config>(unknown)),
+        "/servers/0/user"
+    );
+
+    // keys are escaped, and the root value has an empty path
+    let escaped = "{\"a/b\": {\"c~d\": -1}}";
+    assert_eq!(
+        path(serde_hjson::from_str::<
+            BTreeMap<String, BTreeMap<String, u8>>,
+        >(escaped)),
+        "/a~1b/c~0d"
+    );
+    assert_eq!(path(serde_hjson::from_str::<u8>("x")), "");
+    assert_eq!(
+        path(serde_hjson::from_str_at::<u16>(servers, "/servers/2/port")),
+        "/servers/2/port"
+    );
+    let value = serde_hjson::from_str("{Fixed: {port: -1}}").unwrap();
+    assert_eq!(path(serde_hjson::from_value::<Mode>(value)), "/Fixed/port");
+
+    // syntax errors keep their position
+    match serde_hjson::from_str::<Config>("{servers: [\n  {host: \"a\", port: 1\n") {
+        Err(serde_hjson::Error::Syntax(_, line, _)) => assert!(line >= 2),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // custom errors of Serialize implementations are data errors too
+    struct Failing;
+
+    impl serde::Serialize for Failing {
+        fn serialize<S: serde::Serializer>(&self, _: S) -> std::result::Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("not serializable"))
+        }
+    }
+
+    let err = serde_hjson::to_string(&Failing).unwrap_err();
+    assert_eq!(err.path(), Some(""));
+    assert_eq!(err.to_string(), "not serializable");
+}
+
+#[test]
+pub fn data_error_position() {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug)]
+    #[serde(try_from = "u16")]
+    #[allow(dead_code)]
+    struct Port(u16);
+
+    impl TryFrom<u16> for Port {
+        type Error = String;
+
+        fn try_from(port: u16) -> std::result::Result<Self, String> {
+            if port == 0 {
+                Err("port must not be 0".to_owned())
+            } else {
+                Ok(Port(port))
+            }
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Server {
+        host: String,
+        port: Port,
+    }
+
+    fn position<T: std::fmt::Debug>(result: serde_hjson::Result<T>) -> (String, usize, usize) {
+        match result {
+            Err(serde_hjson::Error::Data(_, path, line, col)) => (path, line, col),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    let text = "host: a\nport: 0\n";
+    let err = serde_hjson::from_str::<Server>(text).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "/port: port must not be 0 at line 2 column 7"
+    );
+    let text = "{\n  host: a\n  port: \"80\"\n}";
+    assert_eq!(
+        position(serde_hjson::from_str::<Server>(text)),
+        ("/port".to_owned(), 3, 9)
+    );
+    let text = "[\n  {\n    host: a\n  }\n]";
+    assert_eq!(
+        position(serde_hjson::from_str::<Vec<Server>>(text)),
+        ("/0".to_owned(), 2, 3)
+    );
+    let text = "host: a\nport: 1\n  user: b\n";
+    assert_eq!(
+        position(serde_hjson::from_str::<Server>(text)),
+        ("/user".to_owned(), 3, 3)
+    );
+    assert_eq!(
+        position(serde_hjson::from_str::<Port>("\n  0")),
+        (String::new(), 2, 3)
+    );
+    let (path, line, _) = position(serde_hjson::from_str::<(u8, bool)>("[1, 2]"));
+    assert_eq!((path.as_str(), line), ("/1", 1));
+
+    // missing fields of a root object without braces are reported at the object
+    for text in ["port: 80", "{port: 80}"] {
+        let err = serde_hjson::from_str::<Server>(text).unwrap_err();
+        assert_eq!(err.to_string(), "missing field `host` at line 1 column 1");
+        assert_eq!(
+            serde_hjson::from_str_at::<Server>(text, "")
+                .unwrap_err()
+                .to_string(),
+            err.to_string()
+        );
+    }
+    // other values are not read as a root object
+    assert_eq!(
+        serde_hjson::from_str::<u8>("x").unwrap_err().to_string(),
+        "invalid type: string \"x\", expected u8 at line 1 column 1"
+    );
+
+    // positions refer to the whole input, and values that were not read from text have none
+    let text = "---\nhost: a\nport: 0\n---\nbody";
+    assert_eq!(
+        position(serde_hjson::from_front_matter::<Server>(text)),
+        ("/port".to_owned(), 3, 7)
+    );
+    let value: Value = serde_hjson::from_str("host: a\nport: 0").unwrap();
+    assert_eq!(
+        position(serde_hjson::from_value::<Server>(value)),
+        ("/port".to_owned(), 0, 0)
+    );
+    let err = serde_hjson::from_value::<u8>(Value::Bool(true)).unwrap_err();
+    assert_eq!(err.to_string(), "invalid type: boolean `true`, expected u8");
+}
+
+#[test]
+pub fn color_formatter() {
+    use serde_hjson::{ColorFormatter, ColorTheme, Commented, HjsonFormatter, JsonFormatter};
+    use std::collections::BTreeMap;
+
+    fn colored<F: serde_hjson::ser::Formatter, T: serde::Serialize>(
+        value: &T,
+        formatter: F,
+    ) -> String {
+        let mut out = Vec::new();
+        value
+            .serialize(&mut serde_hjson::Serializer::with_formatter(
+                &mut out, formatter,
+            ))
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    let value: Value = serde_hjson::from_str("{a: [1, true, null, \"x y\"]}").unwrap();
+    let theme = ColorTheme::new().punctuation("").string("4");
+    assert_eq!(
+        colored(&value, ColorFormatter::with_theme(HjsonFormatter::new(), theme)),
+        "{\n  \x1b[34ma\x1b[0m:\n  [\n    \x1b[36m1\x1b[0m\n    \x1b[33mtrue\x1b[0m\n    \x1b[35mnull\x1b[0m\n    \x1b[4mx y\x1b[0m\n  ]\n}"
+    );
+    assert_eq!(
+        colored(&value, ColorFormatter::new(JsonFormatter::compact())),
+        "\x1b[90m{\x1b[0m\x1b[34m\"a\"\x1b[0m\x1b[90m:\x1b[0m\x1b[90m[\x1b[0m\x1b[36m1\x1b[0m\x1b[90m,\x1b[0m\x1b[33mtrue\x1b[0m\x1b[90m,\x1b[0m\x1b[35mnull\x1b[0m\x1b[90m,\x1b[0m\x1b[32m\"x y\"\x1b[0m\x1b[90m]\x1b[0m\x1b[90m}\x1b[0m"
+    );
+
+    // empty arrays and objects are punctuation too
+    let value: Value = serde_hjson::from_str("{a: [], b: {}}").unwrap();
+    assert_eq!(
+        colored(&value, ColorFormatter::new(HjsonFormatter::new())),
+        "\x1b[90m{\x1b[0m\n  \x1b[34ma\x1b[0m\x1b[90m:\x1b[0m \x1b[90m[]\x1b[0m\n  \x1b[34mb\x1b[0m\x1b[90m:\x1b[0m \x1b[90m{}\x1b[0m\n\x1b[90m}\x1b[0m"
+    );
+    assert_eq!(
+        colored(
+            &Vec::<u8>::new(),
+            ColorFormatter::new(JsonFormatter::compact())
+        ),
+        "\x1b[90m[]\x1b[0m"
+    );
+
+    // without colors the output is the same as that of the inner formatter
+    let mut map = BTreeMap::new();
+    map.insert("port", Commented::trailing(80, "http"));
+    map.insert("text", Commented::new(1, "two\nlines"));
+    assert_eq!(
+        colored(
+            &map,
+            ColorFormatter::with_theme(HjsonFormatter::new(), ColorTheme::plain())
+        ),
+        serde_hjson::to_string(&map).unwrap()
+    );
+    let theme = ColorTheme::plain().comment("2");
+    assert_eq!(
+        colored(
+            &map,
+            ColorFormatter::with_theme(HjsonFormatter::new(), theme)
+        ),
+        "{\n  port: 80 \x1b[2m# http\x1b[0m\n  \x1b[2m# two\n  # lines\x1b[0m\n  text: 1\n}"
+    );
+}
+
+#[test]
+pub fn round_trip_edge_cases() {
+    let cases = [
+        (Value::String(":\\".to_owned()), "\":\\\\\""),
+        (Value::String("a: b".to_owned()), "\"a: b\""),
+        (
+            Value::Array(vec![Value::String(":\\'".to_owned())]),
+            "[\n  \":\\\\'\"\n]",
+        ),
+    ];
+    for (value, expected) in cases {
+        let text = serde_hjson::to_string(&value).unwrap();
+        assert_eq!(text, expected);
+        assert_eq!(serde_hjson::from_str::<Value>(&text).unwrap(), value);
+    }
+
+    // integers that do not fit are read back as floats
+    let text = serde_hjson::to_string(&Value::F64(-1e20)).unwrap();
+    assert_eq!(text, "-100000000000000000000");
+    assert_eq!(
+        serde_hjson::from_str::<Value>(&text).unwrap(),
+        Value::F64(-1e20)
+    );
+}
+
+#[test]
+pub fn json_output() {
+    let value: Value = serde_hjson::from_str(
+        "{int: 1.0\nexp: 1e21\nsmall: 0.000001\ntiny: 1e-7\nneg: -0.0\ntext: 12 monkeys\nml:\n  '''\n  a\n  b\n  '''\nempty: []\nnone: {}\nnested: [{a: null}, [true]]}",
+    )
+    .unwrap();
+    assert_eq!(
+        serde_hjson::to_json_string(&value).unwrap(),
+        r#"{"int":1,"exp":1e+21,"small":0.000001,"tiny":1e-7,"neg":0,"text":"12 monkeys","ml":"a\nb","empty":[],"none":{},"nested":[{"a":null},[true]]}"#
+    );
+    assert_eq!(
+        serde_hjson::to_json_string_pretty(&value).unwrap(),
+        "{\n  \"int\": 1,\n  \"exp\": 1e+21,\n  \"small\": 0.000001,\n  \"tiny\": 1e-7,\n  \"neg\": 0,\n  \"text\": \"12 monkeys\",\n  \"ml\": \"a\\nb\",\n  \"empty\": [],\n  \"none\": {},\n  \"nested\": [\n    {\n      \"a\": null\n    },\n    [\n      true\n    ]\n  ]\n}"
+    );
+    assert_eq!(serde_hjson::to_json_string(&f64::NAN).unwrap(), "null");
+    let json = serde_hjson::to_json_string_pretty(&value).unwrap();
+    assert!(serde_json::from_str::<serde_json::Value>(&json).is_ok());
+}
+
+mod round_trip {
+    use proptest::prelude::*;
+    use serde_hjson::{FormatOptions, Map, Value};
+
+    // strings made of the characters that change how Hjson is written
+    fn arb_string() -> impl Strategy<Value = String> {
+        const CHARS: &[char] = &[
+            'a', 'Z', '0', '5', '-', '+', '.', 'e', ' ', '\t', '\n', '\r', '\'', '"', '\\', '#',
+            '/', '*', ':', ',', '{', '}', '[', ']', '\u{0}', '\u{1f}', '\u{7f}', '\u{2028}',
+            '\u{feff}', 'é', '😀',
+        ];
+        const WORDS: &[&str] = &[
+            "", "true", "false", "null", "0", "-1", "1e5", "0x10", " ", "'''", "a'''b", "//", "/*",
+            "#",
+        ];
+        prop_oneof![
+            prop::collection::vec(prop::sample::select(CHARS), 0..12)
+                .prop_map(|chars| chars.into_iter().collect()),
+            (prop::sample::select(WORDS), prop::sample::select(WORDS))
+                .prop_map(|(a, b)| format!("{}{}", a, b)),
+            any::<String>(),
+        ]
+    }
+
+    fn arb_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            any::<i64>().prop_map(Value::I64),
+            any::<u64>().prop_map(Value::U64),
+            any::<f64>()
+                .prop_filter("written as null", |f| f.is_finite())
+                .prop_map(Value::F64),
+            prop::sample::select(&[0.0, -0.0, 1.0, -1.0, 1e20, -1e20, 1e21][..])
+                .prop_map(Value::F64),
+            arb_string().prop_map(Value::String),
+        ];
+        leaf.prop_recursive(4, 48, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+                prop::collection::vec((arb_string(), inner), 0..6)
+                    .prop_map(|members| Value::Object(members.into_iter().collect::<Map<_, _>>())),
+            ]
+        })
+    }
+
+    fn parse(text: &str) -> Result<Value, TestCaseError> {
+        serde_hjson::from_str(text)
+            .map_err(|err| TestCaseError::fail(format!("{} in {:?}", err, text)))
+    }
+
+    // the value that is read back, as documented on `Value`
+    fn read_back(value: &Value, float_point: bool) -> Value {
+        match *value {
+            Value::I64(i) if i >= 0 => Value::U64(i as u64),
+            Value::F64(f) if !float_point && f.fract() == 0.0 && f.abs() < 1e21 => {
+                let text = serde_hjson::to_string(&f).unwrap();
+                text.parse()
+                    .map(Value::U64)
+                    .or_else(|_| text.parse().map(Value::I64))
+                    .unwrap_or(Value::F64(f))
+            }
+            Value::Array(ref items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| read_back(item, float_point))
+                    .collect(),
+            ),
+            Value::Object(ref members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(key, member)| (key.clone(), read_back(member, float_point)))
+                    .collect(),
+            ),
+            ref other => other.clone(),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn to_string_round_trip(value in arb_value()) {
+            let text = serde_hjson::to_string(&value).unwrap();
+            let parsed = parse(&text)?;
+            prop_assert_eq!(parsed, read_back(&value, false), "{}", text);
+        }
+
+        #[test]
+        fn float_point_round_trip(value in arb_value()) {
+            let options = FormatOptions::new().float_point(true);
+            let text = serde_hjson::to_string_with_options(&value, &options).unwrap();
+            let parsed = parse(&text)?;
+            prop_assert_eq!(parsed, read_back(&value, true), "{}", text);
+        }
+
+        #[test]
+        fn json_round_trip(value in arb_value()) {
+            let text = serde_hjson::to_json_string_pretty(&value).unwrap();
+            let parsed = parse(&text)?;
+            prop_assert_eq!(parsed, read_back(&value, false), "{}", text);
+        }
+    }
+}